@@ -1,7 +1,11 @@
 use clams::prelude::*;
-use clams_bin::mv_files;
+use clams_bin::mv_files::{
+    self, ArchiveKind, ChecksumAlgorithm, CopyOptions, MvFilesError, Preserve, Prompt, Transfer,
+    TransferRate, Verification,
+};
 use failure::{format_err, Error};
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 use structopt::StructOpt;
 use walkdir::WalkDir;
 
@@ -19,14 +23,41 @@ struct Args {
     #[structopt(short = "s", long = "size", default_value = "100M")]
     size: String,
     /// Source directories
-    #[structopt(raw(required = "true", index = "1"))]
+    #[structopt(raw(required_unless = r#""verify""#, index = "1"))]
     sources: Vec<String>,
     /// Destination directory
-    #[structopt(raw(index = "2"))]
-    destination: String,
+    #[structopt(raw(required_unless = r#""verify""#, index = "2"))]
+    destination: Option<String>,
     /// Only show what would be done
     #[structopt(short = "d", long = "dry")]
     dry: bool,
+    /// Ask before each move
+    #[structopt(short = "i", long = "interactive")]
+    interactive: bool,
+    /// Extract matching files from zip, tar, and rar archives; rar requires unrar
+    #[structopt(short = "x", long = "extract")]
+    extract: bool,
+    /// Delete archives after successful extraction
+    #[structopt(long = "delete-archives", requires = "extract")]
+    delete_archives: bool,
+    /// Copy files instead of moving them
+    #[structopt(long = "copy")]
+    copy: bool,
+    /// Limit bandwidth of copies to this many bytes per second, e.g., 20M
+    #[structopt(long = "bwlimit")]
+    bwlimit: Option<String>,
+    /// File attributes to keep when copying: all, none, or a list of timestamps, mode, ownership, xattr; files extracted from archives only keep timestamps and mode
+    #[structopt(long = "preserve", default_value = "all")]
+    preserve: String,
+    /// Use idle I/O priority like `ionice -c 3`
+    #[structopt(long = "ionice")]
+    ionice: bool,
+    /// Append checksums of moved files to a manifest in the destination directory (sha256, blake3)
+    #[structopt(long = "manifest")]
+    manifest: Option<String>,
+    /// Verify files listed in this manifest instead of moving files
+    #[structopt(long = "verify", conflicts_with = "dry")]
+    verify: Option<String>,
     /// do not use colored output
     #[structopt(long = "no-color")]
     no_color: bool,
@@ -42,6 +73,15 @@ struct Args {
 }
 
 fn run(args: Args) -> Result<(), Error> {
+    let manifest = match args.manifest {
+        Some(ref algorithm) => Some(algorithm.parse::<ChecksumAlgorithm>()?),
+        None => None,
+    };
+
+    if let Some(ref path) = args.verify {
+        return verify(Path::new(path), manifest);
+    }
+
     if args.dry {
         warn!(
             "{}",
@@ -50,10 +90,25 @@ fn run(args: Args) -> Result<(), Error> {
     }
 
     let size = mv_files::human_size_to_bytes(&args.size)?;
-    if !PathBuf::from(&args.destination).is_dir() {
+    let bwlimit = match args.bwlimit {
+        Some(ref limit) => Some(mv_files::parse_bwlimit(limit)?),
+        None => None,
+    };
+    let settings = Settings {
+        mode: if args.copy { Mode::Copy } else { Mode::Move },
+        copy_options: CopyOptions {
+            bwlimit,
+            preserve: args.preserve.parse::<Preserve>()?,
+        },
+        manifest,
+        dry: args.dry,
+    };
+    // Safe unwrap, because clap requires destination unless we verify.
+    let destination = args.destination.unwrap();
+    if !PathBuf::from(&destination).is_dir() {
         return Err(format_err!(
             "Destination directory '{}' does not exist.",
-            args.destination
+            destination
         ));
     }
     let extensions = mv_files::parse_extensions(&args.extensions)?;
@@ -76,46 +131,282 @@ fn run(args: Args) -> Result<(), Error> {
         })
         .filter(|p| p.metadata().map(|m| m.len() >= size).unwrap_or(false))
         .map(|p| {
-            let dest_path = mv_files::destination_path(&args.destination, p).unwrap();
+            let dest_path = mv_files::destination_path(&destination, p).unwrap();
             (p, dest_path)
         })
         .collect();
 
+    let moves = if args.interactive {
+        let stdin = std::io::stdin();
+        let mut prompt = Prompt::new(stdin.lock(), std::io::stdout());
+        mv_files::confirm_moves(&mut prompt, moves)?
+    } else {
+        moves
+    };
+
     debug!(
-        "moving with progess bar = {} and dry mode = {} and moves = ({}) {:#?}",
+        "moving with progess bar = {} and settings = {:?} and moves = ({}) {:#?}",
         args.progress_bar,
-        args.dry,
+        settings,
         moves.len(),
         moves
     );
 
+    if args.ionice && !args.dry {
+        mv_files::set_idle_io_priority()?;
+    }
+
     if args.progress_bar {
-        move_files_with_progress_bar(moves.as_slice(), args.dry)
+        move_files_with_progress_bar(moves.as_slice(), &settings)?;
     } else {
-        move_files(moves.as_slice(), args.dry)
+        move_files(moves.as_slice(), &settings)?;
+    }
+
+    if args.extract {
+        let archives: Vec<_> = dir_entries
+            .iter()
+            .map(|e| e.path())
+            .filter(|p| !p.is_dir())
+            .filter_map(|p| ArchiveKind::from_path(p).map(|kind| (p, kind)))
+            .collect();
+        debug!("extracting archives = ({}) {:#?}", archives.len(), archives);
+
+        extract_archives(
+            archives.as_slice(),
+            extensions.as_slice(),
+            size,
+            Path::new(&destination),
+            &settings,
+            args.delete_archives,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn extract_archives(
+    archives: &[(&Path, ArchiveKind)],
+    extensions: &[&str],
+    size: u64,
+    destination: &Path,
+    settings: &Settings,
+    delete_archives: bool,
+) -> Result<(), Error> {
+    for &(archive, kind) in archives {
+        // Safe unwrap because we already checked the paths.
+        let archive_str = archive.to_str().unwrap();
+        let entries = match mv_files::list_archive(archive, kind) {
+            Ok(entries) => mv_files::select_archive_entries(entries, extensions, size),
+            Err(e) => {
+                eprintln!("Failed to read {} because {}", archive_str.red(), e);
+                continue;
+            }
+        };
+        if entries.is_empty() {
+            debug!("no matching files in archive {:?}", archive);
+            continue;
+        }
+
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_ref()).collect();
+        print!(
+            "Extracting {} from {} to {} ...",
+            names.join(", ").yellow(),
+            archive_str.yellow(),
+            destination.to_str().unwrap().yellow()
+        );
+        if settings.dry {
+            println!(" {}", "simulated.".blue());
+            continue;
+        }
+
+        let extracted = match mv_files::extract_archive(
+            archive,
+            kind,
+            &entries,
+            destination,
+            &settings.copy_options,
+        ) {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                eprintln!("Failed to extract {} because {}", archive_str.red(), e);
+                continue;
+            }
+        };
+        if let Some(algorithm) = settings.manifest {
+            for path in &extracted {
+                let _ = mv_files::append_to_manifest(path, algorithm)?;
+            }
+        }
+        println!(" {}.", "done".green());
+
+        if delete_archives {
+            for volume in mv_files::archive_volumes(archive)? {
+                std::fs::remove_file(&volume)
+                    .map_err(|e| MvFilesError::FailedToDeleteArchive { arg: e.to_string() })?;
+                println!("Deleted {}.", volume.to_str().unwrap());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn verify(manifest: &Path, algorithm: Option<ChecksumAlgorithm>) -> Result<(), Error> {
+    let algorithm = algorithm.unwrap_or_else(|| ChecksumAlgorithm::from_manifest_path(manifest));
+    debug!(
+        "verifying manifest = {:?} with algorithm = {:?}",
+        manifest, algorithm
+    );
+
+    let verifications = mv_files::verify_manifest(manifest, algorithm)?;
+
+    let mut failures = 0;
+    for (path, verification) in &verifications {
+        // Safe unwrap because the paths have been read from a text file.
+        let path = path.to_str().unwrap();
+        match verification {
+            Verification::Ok => println!("{}: {}", path, "OK".green()),
+            Verification::Missing => println!("{}: {}", path.red(), "MISSING".red()),
+            Verification::Corrupted => println!("{}: {}", path.red(), "CORRUPTED".red()),
+            Verification::Failed(ref reason) => {
+                println!("{}: {} ({})", path.red(), "FAILED".red(), reason)
+            }
+        }
+        if *verification != Verification::Ok {
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        return Err(format_err!(
+            "{} of {} files are missing, corrupted, or could not be read.",
+            failures,
+            verifications.len()
+        ));
     }
+
+    Ok(())
 }
 
-fn move_files_with_progress_bar(moves: &[(&Path, PathBuf)], dry: bool) -> Result<(), Error> {
+#[derive(Debug)]
+struct Settings {
+    mode: Mode,
+    copy_options: CopyOptions,
+    manifest: Option<ChecksumAlgorithm>,
+    dry: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Move,
+    Copy,
+}
+
+impl Mode {
+    fn verb(self) -> &'static str {
+        match self {
+            Mode::Move => "Moving",
+            Mode::Copy => "Copying",
+        }
+    }
+
+    fn past_verb(self) -> &'static str {
+        match self {
+            Mode::Move => "Moved",
+            Mode::Copy => "Copied",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum TransferError {
+    /// The file has not been transferred.
+    Failed(MvFilesError),
+    /// The file has been transferred, but its checksum has not been recorded in the manifest.
+    Unrecorded(MvFilesError),
+}
+
+/// Transfers `from` to `to` and records the checksum of the source in the manifest, if
+/// requested. The checksum is computed before the transfer so that the manifest can detect
+/// corrupted copies.
+fn transfer<F: FnMut(u64)>(
+    from: &Path,
+    to: &Path,
+    settings: &Settings,
+    progress: F,
+) -> Result<Transfer, TransferError> {
+    let checksum = match settings.manifest {
+        Some(algorithm) => Some(
+            mv_files::checksum_file(from, algorithm)
+                .map(|checksum| (checksum, algorithm))
+                .map_err(TransferError::Failed)?,
+        ),
+        None => None,
+    };
+
+    let transfer = match settings.mode {
+        Mode::Move => mv_files::move_file(from, to, &settings.copy_options, progress),
+        Mode::Copy => mv_files::copy_file(from, to, &settings.copy_options, progress)
+            .map(|bytes| Transfer::Copied { bytes }),
+    }
+    .map_err(TransferError::Failed)?;
+
+    if let Some((checksum, algorithm)) = checksum {
+        let _ = mv_files::append_checksum_to_manifest(to, &checksum, algorithm)
+            .map_err(TransferError::Unrecorded)?;
+    }
+
+    Ok(transfer)
+}
+
+fn report_transfer_error(from: &Path, settings: &Settings, e: &TransferError) {
+    // Safe unwrap because we already checked the paths.
+    let from = from.to_str().unwrap().red();
+    match e {
+        TransferError::Failed(e) => eprintln!("Failed to move {} because {}", from, e),
+        TransferError::Unrecorded(e) => eprintln!(
+            "{} {} but failed to record checksum because {}",
+            settings.mode.past_verb(),
+            from,
+            e
+        ),
+    }
+}
+
+fn format_rate(rate: &TransferRate) -> String {
+    format!(
+        "{}/s (avg {}/s)",
+        mv_files::bytes_to_human_size(rate.current()),
+        mv_files::bytes_to_human_size(rate.average())
+    )
+}
+
+fn move_files_with_progress_bar(
+    moves: &[(&Path, PathBuf)],
+    settings: &Settings,
+) -> Result<(), Error> {
     let pb = ProgressBar::new(moves.len() as u64);
     let style = ProgressStyle::default_clams_bar();
     pb.set_style(style);
 
     for &(from, ref to) in moves {
         // Safe unwrap because we already checked the paths.
-        pb.set_message(&format!(
-            "Moving {} to {} ...",
+        let message = format!(
+            "{} {} to {} ...",
+            settings.mode.verb(),
             from.to_str().unwrap().yellow(),
             to.to_str().unwrap().yellow()
-        ));
-        if !dry {
-            match std::fs::rename(from, to) {
-                Ok(_) => {}
-                Err(e) => eprintln!(
-                    "Failed to move {} because {}",
-                    from.to_str().unwrap().red(),
-                    e
-                ),
+        );
+        pb.set_message(&message);
+        if !settings.dry {
+            let mut rate = TransferRate::new(Instant::now());
+            let progress = |bytes| {
+                if rate.update(bytes, Instant::now()) {
+                    pb.set_message(&format!("{} {}", message, format_rate(&rate)));
+                }
+            };
+            if let Err(e) = transfer(from, to, settings, progress) {
+                report_transfer_error(from, settings, &e);
             }
         }
         pb.inc(1);
@@ -125,24 +416,30 @@ fn move_files_with_progress_bar(moves: &[(&Path, PathBuf)], dry: bool) -> Result
     Ok(())
 }
 
-fn move_files(moves: &[(&Path, PathBuf)], dry: bool) -> Result<(), Error> {
+fn move_files(moves: &[(&Path, PathBuf)], settings: &Settings) -> Result<(), Error> {
     for &(from, ref to) in moves {
         // Safe unwrap because we already checked the paths.
         print!(
-            "Moving {} to {} ...",
+            "{} {} to {} ...",
+            settings.mode.verb(),
             from.to_str().unwrap().yellow(),
             to.to_str().unwrap().yellow()
         );
-        if dry {
+        if settings.dry {
             println!(" {}", "simulated.".blue());
         } else {
-            match std::fs::rename(from, to) {
-                Ok(_) => println!(" {}.", "done".green()),
-                Err(e) => eprintln!(
-                    "Failed to move {} because {}",
-                    from.to_str().unwrap().red(),
-                    e
+            let mut rate = TransferRate::new(Instant::now());
+            let progress = |bytes| {
+                rate.update(bytes, Instant::now());
+            };
+            match transfer(from, to, settings, progress) {
+                Ok(Transfer::Renamed) => println!(" {}.", "done".green()),
+                Ok(Transfer::Copied { .. }) => println!(
+                    " {} ({}/s).",
+                    "done".green(),
+                    mv_files::bytes_to_human_size(rate.average())
                 ),
+                Err(e) => report_transfer_error(from, settings, &e),
             }
         }
     }
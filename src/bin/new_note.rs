@@ -1,7 +1,9 @@
+use chrono::NaiveDate;
 use clams::prelude::*;
 use clams_bin::new_note::*;
 use failure::{format_err, Error};
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 const DEFAULT_CONFIG_FILE_NAME: &str = "new_note.conf";
@@ -16,15 +18,70 @@ struct Args {
     /// Config file
     #[structopt(short = "c", long = "config")]
     config_file: Option<String>,
-    /// title
+    /// Title; asked for together with tags, description, and date if missing and stdin is a terminal
     #[structopt(short = "t", long = "title")]
-    title: String,
-    /// Publication date
-    #[structopt(short = "d", long = "date", default_value = "now")]
-    date: String,
+    title: Option<String>,
+    /// Timezone of the publication date, e.g., 'Europe/Berlin', 'UTC', or '+02:00'; overrides the config
+    #[structopt(long = "tz", raw(allow_hyphen_values = "true"))]
+    timezone: Option<String>,
+    /// Kind of note as configured in the config file
+    #[structopt(short = "k", long = "kind")]
+    kind: Option<String>,
+    /// Publication date, e.g., '2026-10-20 09:00', '2026-10-20', 'tomorrow', '+3d', or 'next monday 09:00'; defaults to now
+    #[structopt(short = "d", long = "date", raw(allow_hyphen_values = "true"))]
+    date: Option<String>,
+    /// Tag; may be used multiple times
+    #[structopt(long = "tag", raw(number_of_values = "1"))]
+    tags: Vec<String>,
+    /// Category; may be used multiple times
+    #[structopt(long = "category", raw(number_of_values = "1"))]
+    categories: Vec<String>,
+    /// Description
+    #[structopt(long = "description")]
+    description: Option<String>,
+    /// Sets frontmatter field, e.g., 'link=https://example.com' or 'aliases=[/a, /b]', except template variables like 'slug'; may be used multiple times
+    #[structopt(long = "set", raw(number_of_values = "1"))]
+    fields: Vec<String>,
+    /// Body of the note, or stdin for '-'
+    #[structopt(long = "body", conflicts_with = "from")]
+    body: Option<String>,
+    /// Imports an existing markdown file; its frontmatter is merged with the generated fields and command line values take precedence
+    #[structopt(long = "from", parse(from_os_str))]
+    from: Option<PathBuf>,
+    /// Creates the note as page bundle and copies this file into it; may be used multiple times
+    #[structopt(long = "asset", parse(from_os_str), raw(number_of_values = "1"))]
+    assets: Vec<PathBuf>,
+    /// Appends -2, -3, etc. to the slug if the note already exists
+    #[structopt(long = "suffix", conflicts_with = "open_existing")]
+    suffix: bool,
+    /// Opens the existing note in the default editor if the note already exists
+    #[structopt(long = "open-existing")]
+    open_existing: bool,
+    /// Appends this journal entry, or stdin for '-', to the day's journal note; creates the note if necessary
+    #[structopt(
+        short = "j",
+        long = "journal",
+        conflicts_with = "suffix",
+        conflicts_with = "open_existing",
+        conflicts_with = "body",
+        conflicts_with = "from",
+        conflicts_with = "assets",
+        conflicts_with = "draft",
+        conflicts_with = "branch"
+    )]
+    journal: Option<String>,
+    /// Creates the note with 'draft: true' in the drafts directory; cf. subcommand publish
+    #[structopt(long = "draft")]
+    draft: bool,
     /// Open new note in default editor
     #[structopt(short = "e", long = "edit")]
     edit: bool,
+    /// Commits the new note, after editing, to the git repository of the notes directory
+    #[structopt(long = "commit")]
+    commit: bool,
+    /// Creates and checks out a new branch once the note has been created
+    #[structopt(long = "branch")]
+    branch: bool,
     /// do not use colored output
     #[structopt(long = "no-color")]
     no_color: bool,
@@ -34,52 +91,620 @@ struct Args {
     /// Verbose mode (-v, -vv, -vvv, etc.)
     #[structopt(short = "v", long = "verbose", parse(from_occurrences))]
     verbosity: u64,
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Lists existing notes
+    #[structopt(name = "list")]
+    List {
+        #[structopt(flatten)]
+        filter: FilterArgs,
+    },
+    /// Searches existing notes for text in their title or body
+    #[structopt(name = "search")]
+    Search {
+        /// Text to search for; case-insensitive
+        text: String,
+        #[structopt(flatten)]
+        filter: FilterArgs,
+    },
+    /// Changes the title of an existing note and moves it, or its page bundle, to the path of the new title
+    #[structopt(name = "retitle")]
+    Retitle {
+        /// Note to retitle
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// New title
+        title: String,
+        /// Adds the note's old URL path to the frontmatter field aliases
+        #[structopt(long = "alias")]
+        alias: bool,
+        /// Kind of note determining its path; defaults to the default kind
+        #[structopt(short = "k", long = "kind")]
+        kind: Option<String>,
+    },
+    /// Publishes a draft by setting its date and moving it to its final location
+    #[structopt(name = "publish")]
+    Publish {
+        /// Draft to publish
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Publication date, e.g., '2026-10-20 09:00' or 'tomorrow'; defaults to now
+        #[structopt(short = "d", long = "date", raw(allow_hyphen_values = "true"))]
+        date: Option<String>,
+        /// Kind of note determining its final location; defaults to the default kind
+        #[structopt(short = "k", long = "kind")]
+        kind: Option<String>,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+struct FilterArgs {
+    /// Only notes with this tag; may be used multiple times
+    #[structopt(long = "tag", raw(number_of_values = "1"))]
+    tags: Vec<String>,
+    /// Only notes with this category; may be used multiple times
+    #[structopt(long = "category", raw(number_of_values = "1"))]
+    categories: Vec<String>,
+    /// Only notes dated on or after this date, e.g., '2020-06-01' or '-4w'
+    #[structopt(long = "since", raw(allow_hyphen_values = "true"))]
+    since: Option<String>,
+    /// Only notes dated on or before this date
+    #[structopt(long = "until", raw(allow_hyphen_values = "true"))]
+    until: Option<String>,
+    /// Only drafts
+    #[structopt(long = "drafts", conflicts_with = "published")]
+    drafts: bool,
+    /// Only published notes, i.e., no drafts
+    #[structopt(long = "published")]
+    published: bool,
+    /// Output as JSON instead of a table
+    #[structopt(long = "json")]
+    json: bool,
+}
+
+impl FilterArgs {
+    fn to_filter(&self, text: Option<&str>) -> Result<NoteFilter, Error> {
+        let day = |date: &Option<String>| -> Result<Option<NaiveDate>, Error> {
+            match date {
+                Some(date) => Ok(Some(str_date_to_date(date)?.naive_local().date())),
+                None => Ok(None),
+            }
+        };
+        let draft = match (self.drafts, self.published) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+
+        Ok(NoteFilter {
+            tags: self.tags.clone(),
+            categories: self.categories.clone(),
+            since: day(&self.since)?,
+            until: day(&self.until)?,
+            draft,
+            text: text.map(|t| t.to_string()),
+        })
+    }
 }
 
 fn run(args: Args) -> Result<(), Error> {
     let mut config_locations = default_locations(DEFAULT_CONFIG_FILE_NAME);
-    if let Some(config) = args.config_file {
+    if let Some(ref config) = args.config_file {
         config_locations.insert(0, config.into());
     }
     let config = NewNoteConfig::smart_load(&config_locations)
         .map_err(|e| format_err!("Failed to load config file because {}", e.to_string()))?;
     debug!("config = {:#?}", config);
 
-    if !PathBuf::from(&config.notes_directory).is_dir() {
+    // Dates are interpreted in the local timezone which is determined by TZ. This relies on chrono
+    // 0.4.9 converting `Local` times via time 0.1, which calls tzset(3) and thus re-reads TZ on
+    // every conversion; check that TZ is still honored when upgrading either crate. TZ must be set
+    // before any other thread is started.
+    if let Some(timezone) = args.timezone.as_ref().or(config.timezone.as_ref()) {
+        if let Some(tz) = timezone_to_tz_env(timezone)? {
+            debug!("Setting TZ={}", tz);
+            std::env::set_var("TZ", tz);
+        }
+    }
+
+    match args.cmd {
+        Some(Command::List { ref filter }) => {
+            list_notes(&config, &filter.to_filter(None)?, filter.json)
+        }
+        Some(Command::Search {
+            ref text,
+            ref filter,
+        }) => list_notes(&config, &filter.to_filter(Some(text))?, filter.json),
+        Some(Command::Retitle {
+            ref path,
+            ref title,
+            alias,
+            ref kind,
+        }) => retitle(&config, path, title, alias, kind.as_deref()),
+        Some(Command::Publish {
+            ref path,
+            ref date,
+            ref kind,
+        }) => publish(&config, path, date.as_deref(), kind.as_deref()),
+        None => new_note(&args, &config),
+    }
+}
+
+fn list_notes(config: &NewNoteConfig, filter: &NoteFilter, json: bool) -> Result<(), Error> {
+    debug!("filter = {:?}", filter);
+
+    let mut notes = Vec::new();
+    for path in find_notes(&config.note_directories()) {
+        match read_note(&path) {
+            Ok(Some(note)) if filter.matches(&note) => notes.push(note),
+            Ok(_) => {}
+            Err(e) => warn!("Skipping note: {}", e),
+        }
+    }
+    notes.sort_by(|a, b| a.date().cmp(&b.date()).then_with(|| a.path.cmp(&b.path)));
+
+    if json {
+        let json = serde_json::to_string_pretty(&notes)
+            .map_err(|e| format_err!("Failed to serialize notes because {}", e.to_string()))?;
+        println!("{}", json);
+    } else {
+        print!("{}", notes_to_table(&notes));
+    }
+
+    Ok(())
+}
+
+fn title_slug(config: &NewNoteConfig, title: &str) -> Result<String, Error> {
+    let slug = slugify(title, &config.slug_options());
+    if slug.is_empty() {
         return Err(format_err!(
-            "Notes directory '{}' does not exist.",
-            config.notes_directory
+            "Title '{}' does not contain any characters usable for a file name.",
+            title
         ));
     }
+    Ok(slug)
+}
+
+fn retitle(
+    config: &NewNoteConfig,
+    path: &Path,
+    title: &str,
+    alias: bool,
+    kind: Option<&str>,
+) -> Result<(), Error> {
+    let canonicalize = |p: &Path| {
+        p.canonicalize()
+            .map_err(|e| format_err!("Failed to find {:?} because {}", p, e.to_string()))
+    };
+    let path = canonicalize(path)?;
+    let notes_directory = config
+        .note_directories()
+        .iter()
+        .filter_map(|d| d.canonicalize().ok())
+        .find(|d| path.starts_with(d))
+        .ok_or_else(|| format_err!("Note {:?} is not in any notes directory.", path))?;
+
+    let note =
+        read_note(&path)?.ok_or_else(|| format_err!("Note {:?} has no frontmatter.", path))?;
+    let slug = title_slug(config, title)?;
+    let kind = config.kind(kind)?;
+    let is_draft = config
+        .drafts_directory()
+        .canonicalize()
+        .map(|d| path.starts_with(d))
+        .unwrap_or(false);
+    let (directory, path_pattern, date) = if is_draft {
+        let date = match note.datetime() {
+            Some(date) => date,
+            None => str_date_to_date("now")?,
+        };
+        (config.drafts_directory(), DRAFT_PATH_PATTERN, date)
+    } else {
+        let date = note
+            .datetime()
+            .ok_or_else(|| format_err!("Note {:?} has no valid date.", path))?;
+        (kind.directory.clone(), kind.path_pattern.as_str(), date)
+    };
+    let new_path = note_target_path(&path, &directory, path_pattern, &date, &slug, &kind.name)?;
+
+    let alias = if alias {
+        note_alias(&path, &notes_directory)
+    } else {
+        None
+    };
+    retitle_note(&path, title, &new_path, alias.as_deref())?;
+    info!("Retitled note {:?} to {:?}", path, new_path);
+
+    Ok(())
+}
+
+fn publish(
+    config: &NewNoteConfig,
+    path: &Path,
+    date: Option<&str>,
+    kind: Option<&str>,
+) -> Result<(), Error> {
+    let note =
+        read_note(path)?.ok_or_else(|| format_err!("Note {:?} has no frontmatter.", path))?;
+    let slug = title_slug(config, note.title())?;
+    let date = str_date_to_date(date.unwrap_or("now"))?;
+    let kind = config.kind(kind)?;
+    let new_path = note_target_path(
+        path,
+        &kind.directory,
+        &kind.path_pattern,
+        &date,
+        &slug,
+        &kind.name,
+    )?;
+
+    publish_note(
+        path,
+        &format_datetime(&date, config.date_format())?,
+        &new_path,
+    )?;
+    info!("Published note {:?} as {:?}", path, new_path);
+
+    Ok(())
+}
+
+fn new_note(args: &Args, config: &NewNoteConfig) -> Result<(), Error> {
+    let imported = match args.from {
+        Some(ref path) => std::fs::read_to_string(path)
+            .map_err(|e| format_err!("Failed to read {:?} because {}", path, e.to_string()))?,
+        None => String::new(),
+    };
+    let (imported_frontmatter, imported_body) = split_note(&imported)?;
+    let body = match args.body {
+        Some(ref body) => Some(read_text(body)?),
+        None if args.from.is_some() => Some(imported_body.to_string()),
+        None => None,
+    };
+
+    let needs_title = args.title.is_none()
+        && imported_frontmatter.get_value("title").is_none()
+        && args.journal.is_none();
+    let answers = if needs_title {
+        ask_for_note(args, config)?
+    } else {
+        None
+    };
+
+    let date = args
+        .date
+        .as_deref()
+        .or(answers.as_ref().and_then(|a| a.date.as_deref()))
+        .or_else(|| imported_frontmatter.get_value("date"))
+        .unwrap_or("now");
+    let date = str_date_to_date(date)?;
+    let journal = config.journal_options();
+
+    let title = match (
+        &args.title,
+        imported_frontmatter.get_value("title"),
+        &args.journal,
+    ) {
+        (Some(title), _, _) => title.clone(),
+        (None, Some(title), _) => title.to_string(),
+        (None, None, Some(_)) => format_datetime(&date, &journal.title)?,
+        (None, None, None) => match answers {
+            Some(ref answers) => answers.title.clone(),
+            None => {
+                return Err(format_err!(
+                    "A title is required to create a note; use -t or --title."
+                ))
+            }
+        },
+    };
+    let title = title.as_str();
 
-    let date = str_date_to_date(&args.date)?;
+    let kind_name = match args.journal {
+        Some(_) => args.kind.as_deref().or(journal.kind.as_deref()),
+        None => args.kind.as_deref(),
+    };
+    let kind = config.kind(kind_name)?;
+    debug!("kind = {:#?}", kind);
 
-    let mut notes_path = PathBuf::from(&config.notes_directory);
-    notes_path.push(date_to_iso_day(&date));
-    notes_path.push(title_to_file_name(&args.title));
+    if !kind.directory.is_dir() {
+        return Err(format_err!(
+            "Notes directory '{}' does not exist.",
+            kind.directory.display()
+        ));
+    }
 
-    if notes_path.is_file() {
+    let slug = slugify(title, &config.slug_options());
+    if slug.is_empty() {
         return Err(format_err!(
-            "Cowardly refusing to overwrite existing file {:?}.",
-            notes_path
+            "Title '{}' does not contain any characters usable for a file name.",
+            title
         ));
     }
 
-    let frontmatter = FrontMatter {
-        title: args.title,
-        date: date_to_iso_day(&date),
+    let frontmatter_date = format_datetime(&date, config.date_format())?;
+    let mut frontmatter = FrontMatter::new(title, &frontmatter_date);
+    frontmatter.fields.extend(kind.fields.clone());
+    frontmatter.merge(imported_frontmatter.clone());
+    frontmatter.set_value("title", title);
+    if args.date.is_some() {
+        frontmatter.set_value("date", &frontmatter_date);
+    }
+    for field in &args.fields {
+        let (key, value) = parse_field(field)?;
+        frontmatter.fields.insert(key, value);
+    }
+    for tag in &args.tags {
+        frontmatter.add_to_list("tags", tag);
+    }
+    for tag in answers.iter().flat_map(|a| &a.tags) {
+        if !frontmatter.get_list("tags").contains(&tag.as_str()) {
+            frontmatter.add_to_list("tags", tag);
+        }
+    }
+    for category in &args.categories {
+        frontmatter.add_to_list("categories", category);
+    }
+    let answered_description = answers.as_ref().and_then(|a| a.description.as_ref());
+    if let Some(description) = args.description.as_ref().or(answered_description) {
+        frontmatter.set_value("description", description);
+    }
+    if args.draft {
+        frontmatter.set_bool("draft", true);
+    }
+
+    let template = NoteTemplate::load(&kind.template, &kind.name)?;
+
+    let git = config.git_options();
+    if args.commit || args.branch {
+        let repository = git_toplevel(&kind.directory)?;
+        debug!("git repository = {:?}", repository);
+    }
+    if let Some(ref text) = args.journal {
+        let text = read_text(text)?;
+        let notes_path =
+            kind.directory
+                .join(note_path(&kind.path_pattern, &date, &slug, &kind.name)?);
+        let entry = journal_entry(&date, &text, &journal.time_format)?;
+        let context = TemplateContext::new(&frontmatter, &slug)
+            .with_slug_options(&config.slug_options())
+            .with_frontmatter_format(kind.frontmatter_format)?
+            .with_required_fields(&kind.required_fields)
+            .with_publication_date(&date);
+
+        // Hooks only run if the day's journal note is created, not for every entry.
+        let hook_note = HookNote {
+            path: &notes_path,
+            title,
+            slug: &slug,
+            date: &frontmatter_date,
+        };
+        if let Some(ref hook) = config.hooks.pre_create {
+            if !notes_path.exists() {
+                run_hook(hook, Path::new(&config.notes_directory), &hook_note)?;
+            }
+        }
+        let update = add_journal_entry(&notes_path, &template, &context, &journal.heading, &entry)?;
+        info!("{:?} journal entry to {:?}", update, notes_path);
+        if args.edit {
+            open_editor(&notes_path)?;
+        }
+        if let (Some(hook), JournalUpdate::Created) = (&config.hooks.post_create, update) {
+            if let Err(e) = run_hook(hook, Path::new(&config.notes_directory), &hook_note) {
+                warn!("Journal {:?} has been created, but {}", notes_path, e);
+            }
+        }
+        if args.commit {
+            commit_note(
+                &kind.directory,
+                &[notes_path],
+                &git.commit_message,
+                &context,
+            )?;
+        }
+        return Ok(());
+    }
+
+    let (directory, path_pattern) = if args.draft {
+        (config.drafts_directory(), DRAFT_PATH_PATTERN)
+    } else {
+        (kind.directory.clone(), kind.path_pattern.as_str())
+    };
+
+    let assets = if args.assets.is_empty() {
+        Vec::new()
+    } else {
+        let pattern_path = note_path(path_pattern, &date, &slug, &kind.name)?;
+        let note_file_name = bundle_path(&pattern_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+            .to_string();
+        check_assets(&args.assets, &note_file_name)?
     };
 
-    debug!("Creating note '{:?}' with title = '{}', publication date = '{}', and launching editor = '{}'", notes_path, &frontmatter.title, &frontmatter.date, args.edit);
+    for n in 1..=MAX_SLUG_SUFFIX {
+        let slug = suffixed_slug(&slug, n);
+        let mut notes_path = directory.join(note_path(path_pattern, &date, &slug, &kind.name)?);
+        if !assets.is_empty() {
+            notes_path = bundle_path(&notes_path);
+        }
 
-    let res = create_note(notes_path.as_path(), &config.notes_template, &frontmatter)
-        .map_err(|e| format_err!("Failed to create note because {}", e.to_string()));
+        debug!(
+            "Creating note '{:?}' with frontmatter = {:?}, and launching editor = '{}'",
+            notes_path, &frontmatter, args.edit
+        );
 
-    if res.is_ok() && args.edit {
-        let _ = open_editor(notes_path.as_path());
+        let mut context = TemplateContext::new(&frontmatter, &slug)
+            .with_slug_options(&config.slug_options())
+            .with_frontmatter_format(kind.frontmatter_format)?
+            .with_required_fields(&kind.required_fields)
+            .with_publication_date(&date)
+            .with_assets(&assets);
+        if let Some(ref body) = body {
+            context = context.with_body(body);
+        }
+        let branch = if args.branch {
+            let branch = render_template(&NoteTemplate::new(&git.branch), &context)?;
+            git_check_new_branch(&kind.directory, &branch)?;
+            Some(branch)
+        } else {
+            None
+        };
+        let hook_note = HookNote {
+            path: &notes_path,
+            title,
+            slug: &slug,
+            date: &frontmatter_date,
+        };
+        if let Some(ref hook) = config.hooks.pre_create {
+            if !notes_path.exists() {
+                run_hook(hook, Path::new(&config.notes_directory), &hook_note)?;
+            }
+        }
+        match create_note(notes_path.as_path(), &template, &context) {
+            Ok(content) => {
+                let bundle_dir = notes_path.parent().unwrap_or(&directory);
+                if let Err(e) = copy_assets(&assets, bundle_dir) {
+                    delete_note(&notes_path, &directory)?;
+                    return Err(format_err!("Failed to create note because {}", e));
+                }
+                if let Some(ref branch) = branch {
+                    if let Err(e) = git_create_branch(&kind.directory, branch) {
+                        for asset in &assets {
+                            let _ = std::fs::remove_file(bundle_dir.join(&asset.name));
+                        }
+                        delete_note(&notes_path, &directory)?;
+                        return Err(format_err!("Failed to create note because {}", e));
+                    }
+                    info!("Created branch '{}'", branch);
+                }
+                let kept = !args.edit || edit_new_note(&notes_path, &content, &directory)?;
+                if let (Some(hook), true) = (&config.hooks.post_create, kept) {
+                    if let Err(e) = run_hook(hook, Path::new(&config.notes_directory), &hook_note) {
+                        warn!("Note {:?} has been created, but {}", notes_path, e);
+                    }
+                }
+                if args.commit && kept {
+                    let paths: Vec<PathBuf> = std::iter::once(notes_path.clone())
+                        .chain(assets.iter().map(|a| bundle_dir.join(&a.name)))
+                        .collect();
+                    commit_note(&kind.directory, &paths, &git.commit_message, &context)?;
+                }
+                return Ok(());
+            }
+            Err(NnError::NoteAlreadyExists { .. }) if args.suffix => continue,
+            Err(NnError::NoteAlreadyExists { .. }) if args.open_existing => {
+                info!("Opening existing note {:?}", notes_path);
+                open_editor(notes_path.as_path())?;
+                return Ok(());
+            }
+            Err(NnError::NoteAlreadyExists { .. }) => {
+                return Err(format_err!(
+                    "Cowardly refusing to overwrite existing file {:?}; use --suffix or --open-existing.",
+                    notes_path
+                ));
+            }
+            Err(e) => {
+                return Err(format_err!(
+                    "Failed to create note because {}",
+                    e.to_string()
+                ))
+            }
+        }
+    }
+
+    Err(format_err!(
+        "Failed to create note because all {} suffixes for slug '{}' are taken.",
+        MAX_SLUG_SUFFIX,
+        slug
+    ))
+}
+
+struct Answers {
+    title: String,
+    tags: Vec<String>,
+    description: Option<String>,
+    date: Option<String>,
+}
+
+/// Asks for the title and for tags, description, and date unless given on the command line;
+/// returns `None` if stdin is not a terminal.
+fn ask_for_note(args: &Args, config: &NewNoteConfig) -> Result<Option<Answers>, Error> {
+    let reads_stdin = args.body.as_deref() == Some("-");
+    if reads_stdin || !is_interactive() {
+        return Ok(None);
+    }
+
+    let stdin = std::io::stdin();
+    let mut prompt = Prompt::new(stdin.lock(), std::io::stdout());
+    let title = prompt.ask_for_title()?;
+    let tags = prompt.ask_for_tags(&known_tags(&config.note_directories()))?;
+    let description = match args.description {
+        Some(_) => None,
+        None => prompt.ask_for_description()?,
+    };
+    let date = match args.date {
+        Some(_) => None,
+        None => prompt.ask_for_date()?,
+    };
+
+    Ok(Some(Answers {
+        title,
+        tags,
+        description,
+        date,
+    }))
+}
+
+/// Returns `text` or reads the text from stdin for `-`.
+fn read_text(text: &str) -> Result<String, Error> {
+    if text != "-" {
+        return Ok(text.to_string());
+    }
+
+    let mut stdin = String::new();
+    std::io::stdin()
+        .read_to_string(&mut stdin)
+        .map_err(|e| format_err!("Failed to read stdin because {}", e.to_string()))?;
+
+    Ok(stdin)
+}
+
+/// Commits `paths` with the commit message rendered from `message`.
+fn commit_note(
+    dir: &Path,
+    paths: &[PathBuf],
+    message: &str,
+    context: &TemplateContext,
+) -> Result<(), Error> {
+    let message = render_template(&NoteTemplate::new(message), context)?;
+    git_commit(dir, paths, &message)?;
+    info!("Committed {:?} with message '{}'", paths, message);
+
+    Ok(())
+}
+
+/// Opens the new note in the editor and returns whether the note has been kept.
+fn edit_new_note(path: &Path, content: &str, notes_directory: &Path) -> Result<bool, Error> {
+    open_editor(path)?;
+
+    if is_note_unchanged(path, content)? {
+        let delete = clams::console::ask_for_confirmation(
+            &format!("Note {:?} is unchanged; delete it? [y/N] ", path),
+            "y",
+        )
+        .map_err(|e| format_err!("Failed to read answer because {}", e.to_string()))?;
+        if delete {
+            delete_note(path, notes_directory)?;
+            info!("Deleted unchanged note {:?}", path);
+            return Ok(false);
+        }
     }
 
-    res
+    Ok(true)
 }
 
 fn main() {
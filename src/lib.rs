@@ -11,169 +11,7 @@ pub mod netatmo {
     }
 }
 
-pub mod new_note {
-    use chrono::prelude::*;
-    use clams::config::prelude::*;
-    use failure::Fail;
-    use handlebars::Handlebars;
-    use serde::{Deserialize, Serialize};
-    use std::env;
-    use std::fs::{self, File};
-    use std::io::prelude::*;
-    use std::process::Command;
-
-    #[derive(Debug, Fail)]
-    pub enum NnError {
-        #[fail(display = "Could to parse publication date because {}", arg)]
-        FailedToParsePublicationDate { arg: String },
-        #[fail(display = "Could to create new note because {}", arg)]
-        FailedToCreateNewNote { arg: String },
-        #[fail(display = "Could not exec editor because {}", arg)]
-        FailedToExecEditor { arg: String },
-        #[fail(display = "Could not render frontmatter template because {}", arg)]
-        FailedToRenderFrontmatterTemplate { arg: String },
-        #[fail(display = "Could not write note file because {}", arg)]
-        FailedToWriteNoteFile { arg: String },
-    }
-
-    #[derive(Config, Debug, Serialize, Deserialize)]
-    pub struct NewNoteConfig {
-        pub notes_directory: String,
-        pub notes_template: String,
-    }
-
-    pub fn title_to_file_name(title: &str) -> String {
-        let mut res = title
-            .to_lowercase()
-            .replace(" ", "-")
-            .replace("'", "-")
-            .replace(",", "-");
-        res.push_str(".md");
-        res
-    }
-
-    pub fn str_date_to_date(date: &str) -> Result<DateTime<Local>, NnError> {
-        match date {
-            "now" => Ok(Local::now()),
-            _ => Local
-                .datetime_from_str(date, "%Y-%m-%d %H:%M")
-                .map_err(|e| NnError::FailedToParsePublicationDate { arg: e.to_string() }),
-        }
-    }
-
-    pub fn date_to_iso_day(dt: &DateTime<Local>) -> String {
-        dt.format("%Y-%m-%d").to_string()
-    }
-
-    pub fn date_to_iso_time(dt: &DateTime<Local>) -> String {
-        dt.format("%Y-%m-%d %H:%M").to_string()
-    }
-
-    #[derive(Debug, Serialize)]
-    pub struct FrontMatter {
-        pub title: String,
-        pub date: String,
-    }
-
-    pub fn create_note(
-        path: &Path,
-        template: &str,
-        frontmatter: &FrontMatter,
-    ) -> Result<(), NnError> {
-        let content = render_template(template, frontmatter)?;
-        let _ = write_content_to_file(&content, &path)?;
-
-        Ok(())
-    }
-
-    pub fn render_template(template: &str, frontmatter: &FrontMatter) -> Result<String, NnError> {
-        let mut handlebars = Handlebars::new();
-        handlebars
-            .register_template_string("frontmatter", template)
-            .map_err(|e| NnError::FailedToRenderFrontmatterTemplate { arg: e.to_string() })?;
-        let text = handlebars
-            .render("frontmatter", frontmatter)
-            .map_err(|e| NnError::FailedToRenderFrontmatterTemplate { arg: e.to_string() })?;
-
-        Ok(text)
-    }
-
-    pub fn write_content_to_file(content: &str, path: &Path) -> Result<(), NnError> {
-        // Make sure, the destnation dir exists.
-        let dir = path
-            .parent()
-            .ok_or_else(|| NnError::FailedToWriteNoteFile {
-                arg: "path does not contain directory".to_string(),
-            })?;
-        if !dir.exists() {
-            fs::create_dir(dir)
-                .map_err(|e| NnError::FailedToWriteNoteFile { arg: e.to_string() })?;
-        }
-        let mut file = File::create(path)
-            .map_err(|e| NnError::FailedToWriteNoteFile { arg: e.to_string() })?;
-        file.write_all(content.as_bytes())
-            .map_err(|e| NnError::FailedToWriteNoteFile { arg: e.to_string() })?;
-
-        Ok(())
-    }
-
-    pub fn open_editor(file: &Path) -> Result<(), NnError> {
-        let editor = env::var_os("EDITOR").unwrap_or_else(|| "vi".to_string().into());
-
-        let _ = Command::new(editor)
-            .arg(file.as_os_str())
-            .spawn()
-            .map_err(|e| NnError::FailedToExecEditor { arg: e.to_string() })?;
-
-        Ok(())
-    }
-
-    #[cfg(test)]
-    mod test {
-        pub use super::*;
-        pub use spectral::prelude::*;
-
-        #[test]
-        fn title_to_file_name_okay() {
-            let title = "This is just a 'Punk, Rock' song";
-            let expected = "this-is-just-a--punk--rock--song.md".to_owned();
-
-            let res = title_to_file_name(title);
-
-            assert_that(&res).is_equal_to(expected);
-        }
-
-        #[test]
-        fn str_date_to_date_okay() {
-            let str_date = "2001-01-01 01:01";
-            let expected = Local.ymd(2001, 01, 01).and_hms(01, 01, 00);
-
-            let res = str_date_to_date(str_date);
-
-            assert_that(&res).is_ok().is_equal_to(expected);
-        }
-
-        #[test]
-        fn date_to_iso_day_okay() {
-            let date = Local.ymd(2001, 01, 01).and_hms(01, 01, 00);
-            let expected = "2001-01-01".to_owned();
-
-            let res = date_to_iso_day(&date);
-
-            assert_that(&res).is_equal_to(expected);
-        }
-
-        #[test]
-        fn date_to_iso_time_okay() {
-            let date = Local.ymd(2001, 01, 01).and_hms(01, 01, 00);
-            let expected = "2001-01-01 01:01".to_owned();
-
-            let res = date_to_iso_time(&date);
-
-            assert_that(&res).is_equal_to(expected);
-        }
-    }
-}
+pub mod new_note;
 
 pub mod pelican_frontmatter {
     use failure::Fail;
@@ -572,7 +410,17 @@ End.
 
 pub mod mv_files {
     use failure::Fail;
-    use std::path::{Path, PathBuf};
+    use filetime::FileTime;
+    use flate2::read::GzDecoder;
+    use sha2::{Digest, Sha256};
+    use std::collections::HashSet;
+    use std::fs::{self, File, Metadata, OpenOptions};
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+    use std::process::Command;
+    use std::str::FromStr;
+    use std::thread;
+    use std::time::{Duration, Instant};
 
     #[derive(Debug, Fail)]
     pub enum MvFilesError {
@@ -586,6 +434,32 @@ pub mod mv_files {
         InvalidExtensionsList { arg: String },
         #[fail(display = "Invalid file name'{}'", arg)]
         InvalidFileName { arg: String },
+        #[fail(display = "Could not copy file because {}", arg)]
+        FailedToCopyFile { arg: String },
+        #[fail(display = "Could not move file because {}", arg)]
+        FailedToMoveFile { arg: String },
+        #[fail(display = "Could not set I/O priority because {}", arg)]
+        FailedToSetIoPriority { arg: String },
+        #[fail(display = "Invalid checksum algorithm '{}'", arg)]
+        InvalidChecksumAlgorithm { arg: String },
+        #[fail(display = "Could not compute checksum because {}", arg)]
+        FailedToComputeChecksum { arg: String },
+        #[fail(display = "Could not read manifest because {}", arg)]
+        FailedToReadManifest { arg: String },
+        #[fail(display = "Could not write manifest because {}", arg)]
+        FailedToWriteManifest { arg: String },
+        #[fail(display = "Could not read answer because {}", arg)]
+        FailedToReadAnswer { arg: String },
+        #[fail(display = "Could not read archive because {}", arg)]
+        FailedToReadArchive { arg: String },
+        #[fail(display = "Could not extract archive because {}", arg)]
+        FailedToExtractArchive { arg: String },
+        #[fail(display = "Could not delete archive because {}", arg)]
+        FailedToDeleteArchive { arg: String },
+        #[fail(display = "Invalid preserve list '{}'", arg)]
+        InvalidPreserveList { arg: String },
+        #[fail(display = "Could not preserve file attributes because {}", arg)]
+        FailedToPreserveAttributes { arg: String },
     }
 
     pub fn human_size_to_bytes(size: &str) -> Result<u64, MvFilesError> {
@@ -619,6 +493,17 @@ pub mod mv_files {
         Ok(size)
     }
 
+    /// Parses a bandwidth limit in bytes per second like `human_size_to_bytes`; zero is rejected
+    /// since nothing could ever be transferred.
+    pub fn parse_bwlimit(limit: &str) -> Result<u64, MvFilesError> {
+        match human_size_to_bytes(limit)? {
+            0 => Err(MvFilesError::InvaildSize {
+                arg: format!("{}; bandwidth limit must be greater than 0", limit),
+            }),
+            bwlimit => Ok(bwlimit),
+        }
+    }
+
     pub fn destination_path<T: AsRef<Path>, S: AsRef<Path>>(
         destination_dir: T,
         file_path: S,
@@ -649,116 +534,2108 @@ pub mod mv_files {
         Ok(res)
     }
 
-    #[cfg(test)]
-    mod test {
-        pub use super::*;
-        pub use spectral::prelude::*;
+    pub fn bytes_to_human_size(bytes: f64) -> String {
+        let scales = ['k', 'M', 'G', 'T', 'P'];
 
-        mod human_size_to_bytes {
-            use super::*;
+        let mut size = bytes;
+        let mut scale = None;
+        for s in &scales {
+            if size < 1024f64 {
+                break;
+            }
+            size /= 1024f64;
+            scale = Some(s);
+        }
 
-            #[test]
-            fn empty() {
-                let res = human_size_to_bytes("");
-                assert_that(&res).is_err();
+        match scale {
+            Some(s) => format!("{:.1}{}", size, s),
+            None => format!("{:.0}", size),
+        }
+    }
+
+    const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+    /// Limits throughput to `rate` bytes per second allowing bursts of up to one second worth of
+    /// data.
+    #[derive(Debug)]
+    pub struct TokenBucket {
+        rate: u64,
+        tokens: f64,
+        last: Instant,
+    }
+
+    impl TokenBucket {
+        pub fn new(rate: u64, now: Instant) -> TokenBucket {
+            TokenBucket {
+                rate,
+                tokens: rate as f64,
+                last: now,
             }
+        }
 
-            #[test]
-            fn nan() {
-                let res = human_size_to_bytes("a10");
-                assert_that(&res).is_err();
+        /// Takes `amount` tokens from the bucket and returns how long to wait until the bucket
+        /// has been refilled, i.e., until the consumption is covered by the rate.
+        pub fn consume(&mut self, amount: u64, now: Instant) -> Duration {
+            let elapsed = now.duration_since(self.last);
+            self.last = now;
+
+            let rate = self.rate as f64;
+            self.tokens = (self.tokens + elapsed.as_secs_f64() * rate).min(rate);
+            self.tokens -= amount as f64;
+
+            if self.tokens < 0f64 {
+                Duration::from_secs_f64(-self.tokens / rate)
+            } else {
+                Duration::from_secs(0)
             }
+        }
+    }
 
-            #[test]
-            fn bytes() {
-                assert_that(&human_size_to_bytes("100"))
-                    .is_ok()
-                    .is_equal_to(100)
+    /// Tracks the current rate over a window of about one second and the average rate over the
+    /// whole transfer.
+    #[derive(Debug)]
+    pub struct TransferRate {
+        start: Instant,
+        last: Instant,
+        total: u64,
+        window_start: Instant,
+        window_bytes: u64,
+        current: f64,
+    }
+
+    impl TransferRate {
+        pub fn new(now: Instant) -> TransferRate {
+            TransferRate {
+                start: now,
+                last: now,
+                total: 0,
+                window_start: now,
+                window_bytes: 0,
+                current: 0f64,
             }
+        }
 
-            #[test]
-            fn kilo_bytes() {
-                assert_that(&human_size_to_bytes("100k"))
-                    .is_ok()
-                    .is_equal_to(100 * 1024)
+        /// Adds `bytes` to the transfer and returns true, if the current rate has been updated.
+        pub fn update(&mut self, bytes: u64, now: Instant) -> bool {
+            self.last = now;
+            self.total += bytes;
+            self.window_bytes += bytes;
+
+            let window = now.duration_since(self.window_start).as_secs_f64();
+            if window >= 1f64 {
+                self.current = self.window_bytes as f64 / window;
+                self.window_start = now;
+                self.window_bytes = 0;
+                true
+            } else {
+                false
             }
+        }
 
-            #[test]
-            fn mega_bytes() {
-                assert_that(&human_size_to_bytes("100M"))
-                    .is_ok()
-                    .is_equal_to(100 * 1024 * 1024)
+        pub fn total(&self) -> u64 {
+            self.total
+        }
+
+        /// Bytes per second during the last window
+        pub fn current(&self) -> f64 {
+            self.current
+        }
+
+        /// Bytes per second since the start of the transfer
+        pub fn average(&self) -> f64 {
+            let elapsed = self.last.duration_since(self.start).as_secs_f64();
+            if elapsed > 0f64 {
+                self.total as f64 / elapsed
+            } else {
+                0f64
             }
+        }
+    }
 
-            #[test]
-            fn giga_bytes() {
-                assert_that(&human_size_to_bytes("100G"))
-                    .is_ok()
-                    .is_equal_to(100 * 1024 * 1024 * 1024)
+    #[derive(Debug, Default)]
+    pub struct CopyOptions {
+        /// Maximum bytes per second
+        pub bwlimit: Option<u64>,
+        pub preserve: Preserve,
+    }
+
+    /// File attributes to carry over from the source to the copy.
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    pub struct Preserve {
+        pub timestamps: bool,
+        pub mode: bool,
+        pub ownership: bool,
+        pub xattr: bool,
+    }
+
+    impl Preserve {
+        pub fn all() -> Preserve {
+            Preserve {
+                timestamps: true,
+                mode: true,
+                ownership: true,
+                xattr: true,
             }
+        }
+    }
 
-            #[test]
-            fn tera_bytes() {
-                assert_that(&human_size_to_bytes("100T"))
-                    .is_ok()
-                    .is_equal_to(100 * 1024 * 1024 * 1024 * 1024)
+    impl FromStr for Preserve {
+        type Err = MvFilesError;
+
+        /// Parses a comma separated list of `all`, `none`, `timestamps`, `mode`, `ownership`, and
+        /// `xattr`.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut preserve = Preserve::default();
+
+            for attribute in s.split(',').map(|a| a.trim()).filter(|a| !a.is_empty()) {
+                match attribute {
+                    "all" => preserve = Preserve::all(),
+                    "none" => preserve = Preserve::default(),
+                    "timestamps" => preserve.timestamps = true,
+                    "mode" => preserve.mode = true,
+                    "ownership" => preserve.ownership = true,
+                    "xattr" => preserve.xattr = true,
+                    _ => {
+                        return Err(MvFilesError::InvalidPreserveList { arg: s.to_string() });
+                    }
+                }
             }
 
-            #[test]
-            fn peta_bytes() {
-                assert_that(&human_size_to_bytes("100P"))
-                    .is_ok()
-                    .is_equal_to(100 * 1024 * 1024 * 1024 * 1024 * 1024)
+            Ok(preserve)
+        }
+    }
+
+    /// Carries the attributes selected by `preserve` over from `from` to `to`; `metadata` must be
+    /// taken from `from` before reading, because reading updates the access time.
+    pub fn preserve_attributes(
+        from: &Path,
+        metadata: &Metadata,
+        to: &Path,
+        preserve: &Preserve,
+    ) -> Result<(), MvFilesError> {
+        // Order matters: Writing xattrs may require write permissions, chown may reset the mode
+        // bits, and all of them update the change time, but not the modification time.
+        if preserve.xattr {
+            copy_xattrs(from, to)?;
+        }
+        if preserve.ownership {
+            copy_ownership(metadata, to)?;
+        }
+        if preserve.mode {
+            fs::set_permissions(to, metadata.permissions())
+                .map_err(|e| MvFilesError::FailedToPreserveAttributes { arg: e.to_string() })?;
+        }
+        if preserve.timestamps {
+            filetime::set_file_times(
+                to,
+                FileTime::from_last_access_time(metadata),
+                FileTime::from_last_modification_time(metadata),
+            )
+            .map_err(|e| MvFilesError::FailedToPreserveAttributes { arg: e.to_string() })?;
+        }
+
+        Ok(())
+    }
+
+    fn copy_xattrs(from: &Path, to: &Path) -> Result<(), MvFilesError> {
+        if !xattr::SUPPORTED_PLATFORM {
+            return Ok(());
+        }
+
+        let names = match xattr::list(from) {
+            Ok(names) => names,
+            // The source file system does not support xattrs, so there's nothing to copy.
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+            Err(e) => return Err(MvFilesError::FailedToPreserveAttributes { arg: e.to_string() }),
+        };
+        for name in names {
+            let value = xattr::get(from, &name)
+                .map_err(|e| MvFilesError::FailedToPreserveAttributes { arg: e.to_string() })?;
+            if let Some(value) = value {
+                match xattr::set(to, &name, &value) {
+                    Ok(_) => {}
+                    Err(ref e) if is_unsettable_xattr(e) => {}
+                    Err(e) => {
+                        return Err(MvFilesError::FailedToPreserveAttributes {
+                            arg: format!("{} for {:?}", e, name),
+                        })
+                    }
+                }
             }
+        }
 
-            #[test]
-            fn unknown_scale() {
-                let res = human_size_to_bytes("100L");
-                assert_that(&res).is_err();
+        Ok(())
+    }
+
+    /// Whether setting an xattr failed only because the destination file system does not support
+    /// xattrs, or because the namespace, e.g., `security.*` or `trusted.*`, requires privileges.
+    /// Like for ownership, we keep what we are permitted to.
+    fn is_unsettable_xattr(e: &io::Error) -> bool {
+        e.raw_os_error() == Some(libc::ENOTSUP) || e.raw_os_error() == Some(libc::EPERM)
+    }
+
+    #[cfg(unix)]
+    fn copy_ownership(metadata: &Metadata, to: &Path) -> Result<(), MvFilesError> {
+        use std::os::unix::fs::MetadataExt;
+
+        match std::os::unix::fs::chown(to, Some(metadata.uid()), Some(metadata.gid())) {
+            Ok(_) => Ok(()),
+            // Only privileged users may give away files, so we keep what we are permitted to.
+            Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => Ok(()),
+            Err(e) => Err(MvFilesError::FailedToPreserveAttributes { arg: e.to_string() }),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn copy_ownership(_: &Metadata, _: &Path) -> Result<(), MvFilesError> {
+        Ok(())
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub enum Transfer {
+        Renamed,
+        Copied { bytes: u64 },
+    }
+
+    /// Copies `reader` to `writer` while honoring the bandwidth limit. `progress` is called with
+    /// the number of bytes of each written chunk.
+    pub fn copy_with_limit<R: Read, W: Write, F: FnMut(u64)>(
+        reader: &mut R,
+        writer: &mut W,
+        bwlimit: Option<u64>,
+        mut progress: F,
+    ) -> io::Result<u64> {
+        let mut bucket = bwlimit.map(|rate| TokenBucket::new(rate, Instant::now()));
+        let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+        let mut total = 0u64;
+
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            writer.write_all(&buf[..len])?;
+            total += len as u64;
+
+            if let Some(ref mut bucket) = bucket {
+                let wait = bucket.consume(len as u64, Instant::now());
+                if wait > Duration::from_secs(0) {
+                    thread::sleep(wait);
+                }
             }
+            progress(len as u64);
         }
+        writer.flush()?;
 
-        mod destination_path {
-            use super::*;
+        Ok(total)
+    }
 
-            #[test]
-            fn destination_path_ok() {
-                let destination_dir = PathBuf::from("/tmp");
-                let abs_file = PathBuf::from("/temp/a_file");
-                let expected = PathBuf::from("/tmp/a_file");
+    const MAX_TMP_FILE_ATTEMPTS: usize = 100;
+
+    /// Creates a new, hidden temporary file next to `to`, which nobody else uses.
+    fn create_tmp_file(to: &Path) -> io::Result<(File, PathBuf)> {
+        let name = to.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+        let pid = std::process::id();
+
+        for n in 0..MAX_TMP_FILE_ATTEMPTS {
+            let tmp_name = if n == 0 {
+                format!(".{}.{}.tmp", name, pid)
+            } else {
+                format!(".{}.{}-{}.tmp", name, pid, n)
+            };
+            let tmp_path = to.with_file_name(tmp_name);
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&tmp_path)
+            {
+                Ok(file) => return Ok((file, tmp_path)),
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
 
-                let res = destination_path(&destination_dir, &abs_file);
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "no unused temporary file name",
+        ))
+    }
 
-                assert_that(&res).is_ok().is_equal_to(expected);
+    /// Copies `from` to `to` replacing an existing file only once the copy is complete. The copy
+    /// is written to a temporary file in the destination directory first, so a failed copy
+    /// neither truncates nor removes an existing `to`.
+    pub fn copy_file<F: FnMut(u64)>(
+        from: &Path,
+        to: &Path,
+        options: &CopyOptions,
+        progress: F,
+    ) -> Result<u64, MvFilesError> {
+        let mut reader =
+            File::open(from).map_err(|e| MvFilesError::FailedToCopyFile { arg: e.to_string() })?;
+        let metadata = reader
+            .metadata()
+            .map_err(|e| MvFilesError::FailedToCopyFile { arg: e.to_string() })?;
+        let (mut writer, tmp_path) = create_tmp_file(to)
+            .map_err(|e| MvFilesError::FailedToCopyFile { arg: e.to_string() })?;
+
+        // A partial or attribute-less copy must not be left behind, in particular not by
+        // `move_file` which keeps the source in that case.
+        let res = copy_with_limit(&mut reader, &mut writer, options.bwlimit, progress)
+            .map_err(|e| MvFilesError::FailedToCopyFile { arg: e.to_string() })
+            .and_then(|bytes| {
+                drop(writer);
+                preserve_attributes(from, &metadata, &tmp_path, &options.preserve).map(|_| bytes)
+            })
+            .and_then(|bytes| {
+                fs::rename(&tmp_path, to)
+                    .map(|_| bytes)
+                    .map_err(|e| MvFilesError::FailedToCopyFile { arg: e.to_string() })
+            });
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        res
+    }
+
+    /// Renames `from` to `to`. If both paths are on different file systems, falls back to copying
+    /// and removing the source afterwards.
+    pub fn move_file<F: FnMut(u64)>(
+        from: &Path,
+        to: &Path,
+        options: &CopyOptions,
+        progress: F,
+    ) -> Result<Transfer, MvFilesError> {
+        match fs::rename(from, to) {
+            Ok(_) => Ok(Transfer::Renamed),
+            Err(ref e) if e.raw_os_error() == Some(libc::EXDEV) => {
+                let bytes = copy_file(from, to, options, progress)?;
+                fs::remove_file(from)
+                    .map_err(|e| MvFilesError::FailedToMoveFile { arg: e.to_string() })?;
+                Ok(Transfer::Copied { bytes })
             }
+            Err(e) => Err(MvFilesError::FailedToMoveFile { arg: e.to_string() }),
         }
+    }
 
-        mod parse_extension {
-            use super::*;
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ChecksumAlgorithm {
+        Sha256,
+        Blake3,
+    }
 
-            #[test]
-            fn empty() {
-                let res = parse_extensions("");
-                assert_that(&res).is_err();
+    impl ChecksumAlgorithm {
+        pub fn manifest_name(self) -> &'static str {
+            match self {
+                ChecksumAlgorithm::Sha256 => "SHA256SUMS",
+                ChecksumAlgorithm::Blake3 => "B3SUMS",
             }
+        }
 
-            #[test]
-            fn one_extension() {
-                let res = parse_extensions("mkv");
-                assert_that(&res).is_ok().has_length(1);
+        /// Guesses the algorithm from the manifest's file name defaulting to SHA-256.
+        pub fn from_manifest_path(path: &Path) -> ChecksumAlgorithm {
+            match path.file_name().and_then(|f| f.to_str()) {
+                Some(name) if name.to_uppercase().starts_with("B3") => ChecksumAlgorithm::Blake3,
+                _ => ChecksumAlgorithm::Sha256,
             }
+        }
+    }
 
-            #[test]
-            fn two_extension() {
-                let res = parse_extensions("mkv,avi");
-                assert_that(&res).is_ok().has_length(2);
+    impl FromStr for ChecksumAlgorithm {
+        type Err = MvFilesError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.to_lowercase().as_ref() {
+                "sha256" => Ok(ChecksumAlgorithm::Sha256),
+                "blake3" | "b3" => Ok(ChecksumAlgorithm::Blake3),
+                _ => Err(MvFilesError::InvalidChecksumAlgorithm { arg: s.to_string() }),
+            }
+        }
+    }
+
+    pub fn checksum<R: Read>(reader: &mut R, algorithm: ChecksumAlgorithm) -> io::Result<String> {
+        let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                loop {
+                    match reader.read(&mut buf)? {
+                        0 => break,
+                        len => hasher.update(&buf[..len]),
+                    }
+                }
+                Ok(format!("{:x}", hasher.finalize()))
+            }
+            ChecksumAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                loop {
+                    match reader.read(&mut buf)? {
+                        0 => break,
+                        len => {
+                            hasher.update(&buf[..len]);
+                        }
+                    }
+                }
+                Ok(hasher.finalize().to_hex().to_string())
             }
+        }
+    }
 
-            #[test]
-            fn two_extension_trailing_sep() {
-                let res = parse_extensions("mkv,avi,");
-                assert_that(&res).is_ok().has_length(2);
+    pub fn checksum_file(
+        path: &Path,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<String, MvFilesError> {
+        let mut file = File::open(path)
+            .map_err(|e| MvFilesError::FailedToComputeChecksum { arg: e.to_string() })?;
+
+        checksum(&mut file, algorithm)
+            .map_err(|e| MvFilesError::FailedToComputeChecksum { arg: e.to_string() })
+    }
+
+    /// An entry of a `sha256sum` style manifest, i.e., `<checksum>  <file name>`.
+    #[derive(Debug, PartialEq)]
+    pub struct ManifestEntry {
+        pub checksum: String,
+        pub file_name: String,
+    }
+
+    impl ManifestEntry {
+        pub fn write(&self) -> String {
+            format!("{}  {}\n", self.checksum, self.file_name)
+        }
+    }
+
+    pub fn parse_manifest<R: BufRead>(reader: R) -> Result<Vec<ManifestEntry>, MvFilesError> {
+        let mut entries = Vec::new();
+
+        for line in reader.lines() {
+            let line =
+                line.map_err(|e| MvFilesError::FailedToReadManifest { arg: e.to_string() })?;
+            if line.trim().is_empty() {
+                continue;
             }
+            let splits: Vec<_> = line.splitn(2, ' ').collect();
+            if splits.len() != 2 {
+                return Err(MvFilesError::FailedToReadManifest {
+                    arg: format!("invalid line '{}'", line),
+                });
+            }
+            // The second separator is either a space for text or an asterisk for binary mode.
+            let file_name = splits[1].trim_start_matches(&[' ', '*'][..]);
+            entries.push(ManifestEntry {
+                checksum: splits[0].to_string(),
+                file_name: file_name.to_string(),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Appends the checksum of `file` to the manifest in the file's directory and returns the
+    /// manifest's path.
+    pub fn append_to_manifest(
+        file: &Path,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<PathBuf, MvFilesError> {
+        let checksum = checksum_file(file, algorithm)?;
+        append_checksum_to_manifest(file, &checksum, algorithm)
+    }
+
+    /// Appends an already computed `checksum` of `file`, e.g., of the source of a move, to the
+    /// manifest in the file's directory and returns the manifest's path.
+    pub fn append_checksum_to_manifest(
+        file: &Path,
+        checksum: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<PathBuf, MvFilesError> {
+        let file_name = file.file_name().and_then(|f| f.to_str()).ok_or_else(|| {
+            MvFilesError::InvalidFileName {
+                arg: format!("{:?}", file),
+            }
+        })?;
+        let manifest_path = file.with_file_name(algorithm.manifest_name());
+
+        let entry = ManifestEntry {
+            checksum: checksum.to_string(),
+            file_name: file_name.to_string(),
+        };
+
+        let mut manifest = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&manifest_path)
+            .map_err(|e| MvFilesError::FailedToWriteManifest { arg: e.to_string() })?;
+        manifest
+            .write_all(entry.write().as_bytes())
+            .map_err(|e| MvFilesError::FailedToWriteManifest { arg: e.to_string() })?;
+
+        Ok(manifest_path)
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub enum Verification {
+        Ok,
+        Missing,
+        Corrupted,
+        /// The checksum could not be computed for this reason
+        Failed(String),
+    }
+
+    /// Rechecks all files listed in `manifest`; file names are relative to the manifest's
+    /// directory. If a file appears more than once, only its last entry counts. Files that cannot
+    /// be read are reported as failed instead of aborting the verification.
+    pub fn verify_manifest(
+        manifest: &Path,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<Vec<(PathBuf, Verification)>, MvFilesError> {
+        let reader = File::open(manifest)
+            .map(BufReader::new)
+            .map_err(|e| MvFilesError::FailedToReadManifest { arg: e.to_string() })?;
+        let entries = parse_manifest(reader)?;
+        let dir = manifest.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut seen = HashSet::new();
+        let mut res: Vec<(PathBuf, Verification)> = Vec::new();
+        for entry in entries.iter().rev() {
+            if !seen.insert(&entry.file_name) {
+                continue;
+            }
+            let path = dir.join(&entry.file_name);
+            let verification = if !path.exists() {
+                Verification::Missing
+            } else {
+                match checksum_file(&path, algorithm) {
+                    Ok(ref checksum) if *checksum == entry.checksum => Verification::Ok,
+                    Ok(_) => Verification::Corrupted,
+                    Err(e) => Verification::Failed(e.to_string()),
+                }
+            };
+            res.push((path, verification));
+        }
+        res.reverse();
+
+        Ok(res)
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub enum Answer {
+        Yes,
+        No,
+        All,
+        Quit,
+        Rename(String),
+    }
+
+    /// Asks the user about each planned move. Reader and writer are usually stdin and stdout.
+    pub struct Prompt<R, W> {
+        reader: R,
+        writer: W,
+    }
+
+    impl<R: BufRead, W: Write> Prompt<R, W> {
+        pub fn new(reader: R, writer: W) -> Prompt<R, W> {
+            Prompt { reader, writer }
+        }
+
+        pub fn ask_for_move(&mut self, from: &Path, to: &Path) -> Result<Answer, MvFilesError> {
+            let size = from.metadata().map(|m| m.len()).unwrap_or(0);
+            self.write(&format!(
+                "Move {} ({}) to {}",
+                from.display(),
+                bytes_to_human_size(size as f64),
+                to.display()
+            ))?;
+            if let Ok(m) = to.metadata() {
+                self.write(&format!(
+                    ", overwriting existing file ({})",
+                    bytes_to_human_size(m.len() as f64)
+                ))?;
+            }
+            self.write("?\n")?;
+
+            loop {
+                self.write("[y]es, [n]o, [a]ll, [q]uit, [r]ename: ")?;
+                let answer = match self.read_line()? {
+                    Some(answer) => answer,
+                    None => return Ok(Answer::Quit),
+                };
+                match answer.to_lowercase().as_ref() {
+                    "y" | "yes" => return Ok(Answer::Yes),
+                    "n" | "no" => return Ok(Answer::No),
+                    "a" | "all" => return Ok(Answer::All),
+                    "q" | "quit" => return Ok(Answer::Quit),
+                    "r" | "rename" => {
+                        if let Some(name) = self.ask_for_file_name()? {
+                            return Ok(Answer::Rename(name));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        /// Returns `None` at the end of input.
+        fn ask_for_file_name(&mut self) -> Result<Option<String>, MvFilesError> {
+            loop {
+                self.write("New file name: ")?;
+                match self.read_line()? {
+                    Some(name) => match invalid_file_name_reason(&name) {
+                        Some(reason) => self.write(&format!("{}\n", reason))?,
+                        None => return Ok(Some(name)),
+                    },
+                    None => return Ok(None),
+                }
+            }
+        }
+
+        fn write(&mut self, text: &str) -> Result<(), MvFilesError> {
+            self.writer
+                .write_all(text.as_bytes())
+                .and_then(|_| self.writer.flush())
+                .map_err(|e| MvFilesError::FailedToReadAnswer { arg: e.to_string() })
+        }
+
+        /// Returns `None` at the end of input.
+        fn read_line(&mut self) -> Result<Option<String>, MvFilesError> {
+            let mut line = String::new();
+            let len = self
+                .reader
+                .read_line(&mut line)
+                .map_err(|e| MvFilesError::FailedToReadAnswer { arg: e.to_string() })?;
+            if len == 0 {
+                Ok(None)
+            } else {
+                Ok(Some(line.trim().to_string()))
+            }
+        }
+    }
+
+    /// Returns why `name` cannot be used as the file name of a renamed destination, if it can't.
+    fn invalid_file_name_reason(name: &str) -> Option<&'static str> {
+        if name.is_empty() {
+            Some("File name must not be empty.")
+        } else if name == "." || name == ".." {
+            Some("File name must not be '.' or '..'.")
+        } else if name.contains('\0') {
+            Some("File name must not contain NUL characters.")
+        } else if name.contains('/') || name.contains(std::path::MAIN_SEPARATOR) {
+            Some("File name must not contain a directory.")
+        } else {
+            None
+        }
+    }
+
+    /// Asks for confirmation of each move and returns the confirmed moves with possibly renamed
+    /// destinations.
+    pub fn confirm_moves<'a, R: BufRead, W: Write>(
+        prompt: &mut Prompt<R, W>,
+        moves: Vec<(&'a Path, PathBuf)>,
+    ) -> Result<Vec<(&'a Path, PathBuf)>, MvFilesError> {
+        let mut confirmed = Vec::new();
+
+        let mut moves = moves.into_iter();
+        while let Some((from, to)) = moves.next() {
+            match prompt.ask_for_move(from, &to)? {
+                Answer::Yes => confirmed.push((from, to)),
+                Answer::No => {}
+                Answer::All => {
+                    confirmed.push((from, to));
+                    confirmed.extend(moves);
+                    break;
+                }
+                Answer::Quit => break,
+                Answer::Rename(name) => {
+                    let to = to.with_file_name(name);
+                    confirmed.push((from, to));
+                }
+            }
+        }
+
+        Ok(confirmed)
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ArchiveKind {
+        Zip,
+        Tar,
+        TarGz,
+        Rar,
+    }
+
+    impl ArchiveKind {
+        /// Detects archives by file name. Only the first volume of a multi-part RAR archive counts
+        /// as archive.
+        pub fn from_path(path: &Path) -> Option<ArchiveKind> {
+            let name = path.file_name()?.to_str()?.to_lowercase();
+
+            if name.ends_with(".zip") {
+                Some(ArchiveKind::Zip)
+            } else if name.ends_with(".tar") {
+                Some(ArchiveKind::Tar)
+            } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+                Some(ArchiveKind::TarGz)
+            } else if name.ends_with(".rar") {
+                match rar_part(&name) {
+                    Some((_, part)) if part != 1 => None,
+                    _ => Some(ArchiveKind::Rar),
+                }
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Splits new style multi-part RAR names like `movie.part02.rar` into base name and part.
+    fn rar_part(name: &str) -> Option<(&str, u32)> {
+        let stem = name.get(..name.len().checked_sub(".rar".len())?)?;
+        let idx = stem.rfind(".part")?;
+        let part = stem[idx + ".part".len()..].parse().ok()?;
+
+        Some((&stem[..idx], part))
+    }
+
+    /// Checks if `candidate` belongs to the multi-part archive starting with `archive`, e.g.,
+    /// `movie.part2.rar` to `movie.part1.rar` or `movie.r00` to `movie.rar`.
+    pub fn is_archive_volume(archive: &str, candidate: &str) -> bool {
+        let archive = archive.to_lowercase();
+        let candidate = candidate.to_lowercase();
+
+        if archive == candidate {
+            return true;
+        }
+        if !archive.ends_with(".rar") {
+            return false;
+        }
+
+        if let Some((base, _)) = rar_part(&archive) {
+            return rar_part(&candidate).is_some_and(|(b, _)| b == base);
+        }
+
+        let stem = &archive[..archive.len() - ".rar".len()];
+        match candidate
+            .strip_prefix(stem)
+            .and_then(|rest| rest.strip_prefix('.'))
+        {
+            Some(ext) => {
+                ext.len() == 3
+                    && (ext.starts_with('r') || ext.starts_with('s'))
+                    && ext[1..].chars().all(|c| c.is_ascii_digit())
+            }
+            None => false,
+        }
+    }
+
+    /// Returns all volumes of `archive` including itself.
+    pub fn archive_volumes(archive: &Path) -> Result<Vec<PathBuf>, MvFilesError> {
+        let name = archive
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| MvFilesError::InvalidFileName {
+                arg: format!("{:?}", archive),
+            })?;
+        let dir = match archive.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let mut volumes: Vec<_> = fs::read_dir(dir)
+            .map_err(|e| MvFilesError::FailedToDeleteArchive { arg: e.to_string() })?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|f| f.to_str())
+                    .is_some_and(|f| is_archive_volume(name, f))
+            })
+            .collect();
+        volumes.sort();
+
+        Ok(volumes)
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ArchiveEntry {
+        pub name: String,
+        pub size: u64,
+    }
+
+    /// Lists all files in `archive`. RAR archives require an external `unrar`.
+    pub fn list_archive(
+        archive: &Path,
+        kind: ArchiveKind,
+    ) -> Result<Vec<ArchiveEntry>, MvFilesError> {
+        let mut entries = Vec::new();
+
+        match kind {
+            ArchiveKind::Zip => {
+                let mut zip = zip_archive(archive)?;
+                for i in 0..zip.len() {
+                    let file = zip
+                        .by_index(i)
+                        .map_err(|e| MvFilesError::FailedToReadArchive { arg: e.to_string() })?;
+                    if file.is_file() {
+                        entries.push(ArchiveEntry {
+                            name: file.name().to_string(),
+                            size: file.size(),
+                        });
+                    }
+                }
+            }
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                let mut tar = tar_archive(archive, kind)?;
+                let tar_entries = tar
+                    .entries()
+                    .map_err(|e| MvFilesError::FailedToReadArchive { arg: e.to_string() })?;
+                for entry in tar_entries {
+                    let entry = entry
+                        .map_err(|e| MvFilesError::FailedToReadArchive { arg: e.to_string() })?;
+                    if entry.header().entry_type().is_file() {
+                        entries.push(ArchiveEntry {
+                            name: tar_entry_name(&entry)?,
+                            size: entry.size(),
+                        });
+                    }
+                }
+            }
+            ArchiveKind::Rar => {
+                let output = unrar()
+                    .args(["lt", "-idc", "--"])
+                    .arg(archive)
+                    .output()
+                    .map_err(|e| MvFilesError::FailedToReadArchive {
+                        arg: unrar_error(&e),
+                    })?;
+                if !output.status.success() {
+                    return Err(MvFilesError::FailedToReadArchive {
+                        arg: format!("unrar failed with {}", output.status),
+                    });
+                }
+                entries = parse_unrar_listing(&String::from_utf8_lossy(&output.stdout));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Parses the technical listing of `unrar lt`.
+    pub fn parse_unrar_listing(listing: &str) -> Vec<ArchiveEntry> {
+        let mut entries = Vec::new();
+
+        let mut name = None;
+        let mut is_file = true;
+        for line in listing.lines() {
+            let line = line.trim();
+            if let Some(n) = line.strip_prefix("Name: ") {
+                name = Some(n.to_string());
+                is_file = true;
+            } else if let Some(t) = line.strip_prefix("Type: ") {
+                is_file = t == "File";
+            } else if let Some(size) = line.strip_prefix("Size: ") {
+                match (name.take(), size.parse()) {
+                    (Some(name), Ok(size)) if is_file => entries.push(ArchiveEntry { name, size }),
+                    _ => {}
+                }
+            }
+        }
+
+        entries
+    }
+
+    /// Selects entries by the same criteria as plain files, i.e., extension and minimum size.
+    pub fn select_archive_entries(
+        entries: Vec<ArchiveEntry>,
+        extensions: &[&str],
+        size: u64,
+    ) -> Vec<ArchiveEntry> {
+        entries
+            .into_iter()
+            .filter(|e| {
+                Path::new(&e.name)
+                    .extension()
+                    .and_then(|x| x.to_str())
+                    .is_some_and(|x| extensions.contains(&x))
+            })
+            .filter(|e| e.size >= size)
+            .collect()
+    }
+
+    /// Extracts `entries` of `archive` into the flat `destination_dir` and returns the paths of
+    /// the extracted files. Existing files are never overwritten. Of the preserve options, only
+    /// timestamps and mode apply, since archives do not reliably carry ownership and xattrs; unrar
+    /// restores the attributes of RAR entries itself.
+    pub fn extract_archive(
+        archive: &Path,
+        kind: ArchiveKind,
+        entries: &[ArchiveEntry],
+        destination_dir: &Path,
+        options: &CopyOptions,
+    ) -> Result<Vec<PathBuf>, MvFilesError> {
+        let mut extracted = Vec::new();
+
+        match kind {
+            ArchiveKind::Zip => {
+                let mut zip = zip_archive(archive)?;
+                for entry in entries {
+                    let mut file = zip
+                        .by_name(&entry.name)
+                        .map_err(|e| MvFilesError::FailedToExtractArchive { arg: e.to_string() })?;
+                    let to = destination_path(destination_dir, &entry.name)?;
+                    let attributes = EntryAttributes {
+                        mtime: zip_mtime(&file.last_modified()),
+                        mode: file.unix_mode(),
+                    };
+                    extract_entry(&mut file, &to, &attributes, options)?;
+                    extracted.push(to);
+                }
+            }
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                let mut tar = tar_archive(archive, kind)?;
+                let tar_entries = tar
+                    .entries()
+                    .map_err(|e| MvFilesError::FailedToExtractArchive { arg: e.to_string() })?;
+                for entry in tar_entries {
+                    let mut entry = entry
+                        .map_err(|e| MvFilesError::FailedToExtractArchive { arg: e.to_string() })?;
+                    let name = tar_entry_name(&entry)?;
+                    if entries.iter().any(|e| e.name == name) {
+                        let to = destination_path(destination_dir, &name)?;
+                        let header = entry.header();
+                        let attributes = EntryAttributes {
+                            mtime: header.mtime().ok().map(|mtime| mtime as i64),
+                            mode: header.mode().ok(),
+                        };
+                        extract_entry(&mut entry, &to, &attributes, options)?;
+                        extracted.push(to);
+                    }
+                }
+            }
+            ArchiveKind::Rar => {
+                // unrar only treats the destination as directory with a trailing separator.
+                let destination = format!("{}{}", destination_dir.display(), MAIN_SEPARATOR);
+                for entry in entries {
+                    let to = destination_path(destination_dir, &entry.name)?;
+                    if to.exists() {
+                        return Err(MvFilesError::FailedToExtractArchive {
+                            arg: format!("'{}' already exists", to.display()),
+                        });
+                    }
+                    let status = unrar()
+                        .args(["e", "-y", "-o-", "-idq", "--"])
+                        .arg(archive)
+                        .arg(&entry.name)
+                        .arg(&destination)
+                        .status()
+                        .map_err(|e| MvFilesError::FailedToExtractArchive {
+                            arg: unrar_error(&e),
+                        })?;
+                    if !status.success() {
+                        return Err(MvFilesError::FailedToExtractArchive {
+                            arg: format!("unrar failed with {}", status),
+                        });
+                    }
+                    extracted.push(to);
+                }
+            }
+        }
+
+        Ok(extracted)
+    }
+
+    fn zip_archive(archive: &Path) -> Result<zip::ZipArchive<File>, MvFilesError> {
+        let file = File::open(archive)
+            .map_err(|e| MvFilesError::FailedToReadArchive { arg: e.to_string() })?;
+
+        zip::ZipArchive::new(file)
+            .map_err(|e| MvFilesError::FailedToReadArchive { arg: e.to_string() })
+    }
+
+    fn tar_archive(
+        archive: &Path,
+        kind: ArchiveKind,
+    ) -> Result<tar::Archive<Box<dyn Read>>, MvFilesError> {
+        let file = File::open(archive)
+            .map_err(|e| MvFilesError::FailedToReadArchive { arg: e.to_string() })?;
+        let reader: Box<dyn Read> = if kind == ArchiveKind::TarGz {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+
+        Ok(tar::Archive::new(reader))
+    }
+
+    fn tar_entry_name<R: Read>(entry: &tar::Entry<R>) -> Result<String, MvFilesError> {
+        entry
+            .path()
+            .map(|p| p.to_string_lossy().to_string())
+            .map_err(|e| MvFilesError::FailedToReadArchive { arg: e.to_string() })
+    }
+
+    /// Attributes of an archive entry as far as the archive stores them.
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    pub struct EntryAttributes {
+        /// Modification time in seconds since the epoch
+        pub mtime: Option<i64>,
+        pub mode: Option<u32>,
+    }
+
+    /// Zip archives store local time without timezone.
+    fn zip_mtime(datetime: &zip::DateTime) -> Option<i64> {
+        use chrono::TimeZone;
+
+        let naive = chrono::NaiveDate::from_ymd_opt(
+            i32::from(datetime.year()),
+            u32::from(datetime.month()),
+            u32::from(datetime.day()),
+        )?
+        .and_hms_opt(
+            u32::from(datetime.hour()),
+            u32::from(datetime.minute()),
+            u32::from(datetime.second()),
+        )?;
+
+        chrono::Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|t| t.timestamp())
+    }
+
+    /// Extracts a single entry to the new file `to`; if extracting fails, `to` is removed again.
+    fn extract_entry<R: Read>(
+        reader: &mut R,
+        to: &Path,
+        attributes: &EntryAttributes,
+        options: &CopyOptions,
+    ) -> Result<(), MvFilesError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(to)
+            .map_err(|e| MvFilesError::FailedToExtractArchive {
+                arg: format!("'{}' {}", to.display(), e),
+            })?;
+        let res = copy_with_limit(reader, &mut file, options.bwlimit, |_| {})
+            .map_err(|e| MvFilesError::FailedToExtractArchive { arg: e.to_string() })
+            .and_then(|_| {
+                drop(file);
+                set_entry_attributes(to, attributes, &options.preserve)
+            });
+        if res.is_err() {
+            let _ = fs::remove_file(to);
+        }
+
+        res
+    }
+
+    fn set_entry_attributes(
+        to: &Path,
+        attributes: &EntryAttributes,
+        preserve: &Preserve,
+    ) -> Result<(), MvFilesError> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            if let (true, Some(mode)) = (preserve.mode, attributes.mode) {
+                fs::set_permissions(to, fs::Permissions::from_mode(mode & 0o7777))
+                    .map_err(|e| MvFilesError::FailedToPreserveAttributes { arg: e.to_string() })?;
+            }
+        }
+        if let (true, Some(mtime)) = (preserve.timestamps, attributes.mtime) {
+            filetime::set_file_mtime(to, FileTime::from_unix_time(mtime, 0))
+                .map_err(|e| MvFilesError::FailedToPreserveAttributes { arg: e.to_string() })?;
+        }
+
+        Ok(())
+    }
+
+    fn unrar() -> Command {
+        Command::new("unrar")
+    }
+
+    fn unrar_error(e: &io::Error) -> String {
+        if e.kind() == io::ErrorKind::NotFound {
+            "unrar is not installed".to_string()
+        } else {
+            e.to_string()
+        }
+    }
+
+    /// Lowers this process' I/O scheduling class to idle just like `ionice -c 3`.
+    #[cfg(target_os = "linux")]
+    pub fn set_idle_io_priority() -> Result<(), MvFilesError> {
+        const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+        const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+        const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+        let ioprio = IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT;
+        // Safe because ioprio_set only reads its integer arguments.
+        let res = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) };
+        if res == -1 {
+            return Err(MvFilesError::FailedToSetIoPriority {
+                arg: io::Error::last_os_error().to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn set_idle_io_priority() -> Result<(), MvFilesError> {
+        Err(MvFilesError::FailedToSetIoPriority {
+            arg: "not supported on this platform".to_string(),
+        })
+    }
+
+    #[cfg(test)]
+    mod test {
+        pub use super::*;
+        pub use crate::test_util::TempDir;
+        pub use spectral::prelude::*;
+
+        mod human_size_to_bytes {
+            use super::*;
+
+            #[test]
+            fn empty() {
+                let res = human_size_to_bytes("");
+                assert_that(&res).is_err();
+            }
+
+            #[test]
+            fn nan() {
+                let res = human_size_to_bytes("a10");
+                assert_that(&res).is_err();
+            }
+
+            #[test]
+            fn bytes() {
+                assert_that(&human_size_to_bytes("100"))
+                    .is_ok()
+                    .is_equal_to(100)
+            }
+
+            #[test]
+            fn kilo_bytes() {
+                assert_that(&human_size_to_bytes("100k"))
+                    .is_ok()
+                    .is_equal_to(100 * 1024)
+            }
+
+            #[test]
+            fn mega_bytes() {
+                assert_that(&human_size_to_bytes("100M"))
+                    .is_ok()
+                    .is_equal_to(100 * 1024 * 1024)
+            }
+
+            #[test]
+            fn giga_bytes() {
+                assert_that(&human_size_to_bytes("100G"))
+                    .is_ok()
+                    .is_equal_to(100 * 1024 * 1024 * 1024)
+            }
+
+            #[test]
+            fn tera_bytes() {
+                assert_that(&human_size_to_bytes("100T"))
+                    .is_ok()
+                    .is_equal_to(100 * 1024 * 1024 * 1024 * 1024)
+            }
+
+            #[test]
+            fn peta_bytes() {
+                assert_that(&human_size_to_bytes("100P"))
+                    .is_ok()
+                    .is_equal_to(100 * 1024 * 1024 * 1024 * 1024 * 1024)
+            }
+
+            #[test]
+            fn unknown_scale() {
+                let res = human_size_to_bytes("100L");
+                assert_that(&res).is_err();
+            }
+
+            #[test]
+            fn bwlimit() {
+                assert_that(&parse_bwlimit("10M"))
+                    .is_ok()
+                    .is_equal_to(10 * 1024 * 1024);
+                assert_that(&parse_bwlimit("0")).is_err();
+                assert_that(&parse_bwlimit("0k")).is_err();
+            }
+        }
+
+        mod destination_path {
+            use super::*;
+
+            #[test]
+            fn destination_path_ok() {
+                let destination_dir = PathBuf::from("/tmp");
+                let abs_file = PathBuf::from("/temp/a_file");
+                let expected = PathBuf::from("/tmp/a_file");
+
+                let res = destination_path(&destination_dir, &abs_file);
+
+                assert_that(&res).is_ok().is_equal_to(expected);
+            }
+        }
+
+        mod parse_extension {
+            use super::*;
+
+            #[test]
+            fn empty() {
+                let res = parse_extensions("");
+                assert_that(&res).is_err();
+            }
+
+            #[test]
+            fn one_extension() {
+                let res = parse_extensions("mkv");
+                assert_that(&res).is_ok().has_length(1);
+            }
+
+            #[test]
+            fn two_extension() {
+                let res = parse_extensions("mkv,avi");
+                assert_that(&res).is_ok().has_length(2);
+            }
+
+            #[test]
+            fn two_extension_trailing_sep() {
+                let res = parse_extensions("mkv,avi,");
+                assert_that(&res).is_ok().has_length(2);
+            }
+        }
+
+        mod bytes_to_human_size {
+            use super::*;
+
+            #[test]
+            fn bytes() {
+                assert_that(&bytes_to_human_size(100f64)).is_equal_to("100".to_owned())
+            }
+
+            #[test]
+            fn kilo_bytes() {
+                assert_that(&bytes_to_human_size(1536f64)).is_equal_to("1.5k".to_owned())
+            }
+
+            #[test]
+            fn mega_bytes() {
+                assert_that(&bytes_to_human_size(20f64 * 1024f64 * 1024f64))
+                    .is_equal_to("20.0M".to_owned())
+            }
+
+            #[test]
+            fn roundtrip() {
+                let bytes = human_size_to_bytes("3G").unwrap();
+                assert_that(&bytes_to_human_size(bytes as f64)).is_equal_to("3.0G".to_owned())
+            }
+        }
+
+        mod checksum {
+            use super::*;
+
+            #[test]
+            fn sha256() {
+                let res = checksum(&mut "abc".as_bytes(), ChecksumAlgorithm::Sha256);
+
+                assert_that(&res).is_ok().is_equal_to(
+                    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_owned(),
+                );
+            }
+
+            #[test]
+            fn blake3() {
+                let res = checksum(&mut "abc".as_bytes(), ChecksumAlgorithm::Blake3);
+
+                assert_that(&res).is_ok().is_equal_to(
+                    "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85".to_owned(),
+                );
+            }
+
+            #[test]
+            fn algorithm_from_str() {
+                assert_that(&"SHA256".parse::<ChecksumAlgorithm>())
+                    .is_ok()
+                    .is_equal_to(ChecksumAlgorithm::Sha256);
+                assert_that(&"blake3".parse::<ChecksumAlgorithm>())
+                    .is_ok()
+                    .is_equal_to(ChecksumAlgorithm::Blake3);
+                assert_that(&"md5".parse::<ChecksumAlgorithm>()).is_err();
+            }
+
+            #[test]
+            fn algorithm_from_manifest_path() {
+                let res = ChecksumAlgorithm::from_manifest_path(Path::new("/tmp/B3SUMS"));
+                assert_that(&res).is_equal_to(ChecksumAlgorithm::Blake3);
+
+                let res = ChecksumAlgorithm::from_manifest_path(Path::new("/tmp/SHA256SUMS"));
+                assert_that(&res).is_equal_to(ChecksumAlgorithm::Sha256);
+            }
+        }
+
+        mod manifest {
+            use super::*;
+
+            #[test]
+            fn parse_manifest_okay() {
+                let manifest = "abc  movie 1.mkv\ndef *movie_2.avi\n\n";
+                let expected = vec![
+                    ManifestEntry {
+                        checksum: "abc".to_owned(),
+                        file_name: "movie 1.mkv".to_owned(),
+                    },
+                    ManifestEntry {
+                        checksum: "def".to_owned(),
+                        file_name: "movie_2.avi".to_owned(),
+                    },
+                ];
+
+                let res = parse_manifest(manifest.as_bytes());
+
+                assert_that(&res).is_ok().is_equal_to(expected);
+            }
+
+            #[test]
+            fn parse_manifest_invalid_line() {
+                let res = parse_manifest("abc".as_bytes());
+
+                assert_that(&res).is_err();
+            }
+
+            #[test]
+            fn write_and_parse_roundtrip() {
+                let entry = ManifestEntry {
+                    checksum: "abc".to_owned(),
+                    file_name: "movie 1.mkv".to_owned(),
+                };
+
+                let res = parse_manifest(entry.write().as_bytes());
+
+                assert_that(&res).is_ok().is_equal_to(vec![entry]);
+            }
+
+            #[test]
+            fn append_given_checksum() {
+                let dir = TempDir::new("mv_files-checksum");
+
+                let res = append_checksum_to_manifest(
+                    &dir.join("movie.mkv"),
+                    "abc",
+                    ChecksumAlgorithm::Blake3,
+                );
+                let manifest = fs::read_to_string(dir.join("B3SUMS"));
+
+                assert_that(&res).is_ok();
+                assert_that(&manifest.unwrap()).is_equal_to("abc  movie.mkv\n".to_string());
+            }
+
+            #[test]
+            fn append_and_verify() {
+                let dir = TempDir::new("mv_files-manifest");
+                for name in &["ok.mkv", "corrupted.mkv", "missing.mkv"] {
+                    fs::write(dir.join(name), name.as_bytes()).unwrap();
+                    append_to_manifest(&dir.join(name), ChecksumAlgorithm::Sha256).unwrap();
+                }
+                fs::write(dir.join("corrupted.mkv"), b"bit rot").unwrap();
+                fs::remove_file(dir.join("missing.mkv")).unwrap();
+                // Unreadable, since it is a directory, and listed twice with the last entry counting
+                fs::create_dir(dir.join("unreadable.mkv")).unwrap();
+                let mut manifest = OpenOptions::new()
+                    .append(true)
+                    .open(dir.join("SHA256SUMS"))
+                    .unwrap();
+                manifest
+                    .write_all(b"abc  unreadable.mkv\nabc  ok.mkv\nabc  unreadable.mkv\n")
+                    .unwrap();
+
+                let res = verify_manifest(&dir.join("SHA256SUMS"), ChecksumAlgorithm::Sha256);
+
+                let mut res = res.unwrap();
+                let unreadable = res.pop();
+                assert_that(&res).is_equal_to(vec![
+                    (dir.join("corrupted.mkv"), Verification::Corrupted),
+                    (dir.join("missing.mkv"), Verification::Missing),
+                    (dir.join("ok.mkv"), Verification::Corrupted),
+                ]);
+                assert_that(&matches!(
+                    unreadable,
+                    Some((ref path, Verification::Failed(_))) if *path == dir.join("unreadable.mkv")
+                ))
+                .is_true();
+            }
+        }
+
+        mod prompt {
+            use super::*;
+
+            fn moves() -> Vec<(&'static Path, PathBuf)> {
+                vec![
+                    (
+                        Path::new("tests/data/movie_1.avi"),
+                        PathBuf::from("/tmp/movie_1.avi"),
+                    ),
+                    (
+                        Path::new("tests/data/movie_2.mp4"),
+                        PathBuf::from("/tmp/movie_2.mp4"),
+                    ),
+                    (
+                        Path::new("tests/data/movie_3.mkv"),
+                        PathBuf::from("/tmp/movie_3.mkv"),
+                    ),
+                ]
+            }
+
+            fn confirm(input: &str) -> (Vec<(&'static Path, PathBuf)>, String) {
+                let mut output = Vec::new();
+                let res = {
+                    let mut prompt = Prompt::new(input.as_bytes(), &mut output);
+                    confirm_moves(&mut prompt, moves())
+                };
+
+                (res.unwrap(), String::from_utf8(output).unwrap())
+            }
+
+            #[test]
+            fn yes_and_no() {
+                let (res, _) = confirm("y\nn\nyes\n");
+
+                assert_that(&res).is_equal_to(vec![
+                    (
+                        Path::new("tests/data/movie_1.avi"),
+                        PathBuf::from("/tmp/movie_1.avi"),
+                    ),
+                    (
+                        Path::new("tests/data/movie_3.mkv"),
+                        PathBuf::from("/tmp/movie_3.mkv"),
+                    ),
+                ]);
+            }
+
+            #[test]
+            fn all() {
+                let (res, _) = confirm("n\na\n");
+
+                assert_that(&res).has_length(2);
+            }
+
+            #[test]
+            fn quit() {
+                let (res, _) = confirm("y\nq\ny\n");
+
+                assert_that(&res).is_equal_to(vec![(
+                    Path::new("tests/data/movie_1.avi"),
+                    PathBuf::from("/tmp/movie_1.avi"),
+                )]);
+            }
+
+            #[test]
+            fn end_of_input_quits() {
+                let (res, _) = confirm("y\n");
+
+                assert_that(&res).has_length(1);
+            }
+
+            #[test]
+            fn rename() {
+                let (res, output) = confirm("r\nsub/dir.avi\nrenamed.avi\nn\nn\n");
+
+                assert_that(&res).is_equal_to(vec![(
+                    Path::new("tests/data/movie_1.avi"),
+                    PathBuf::from("/tmp/renamed.avi"),
+                )]);
+                assert_that(&output).contains("File name must not contain a directory.");
+            }
+
+            #[test]
+            fn rename_rejects_invalid_file_names() {
+                let (res, output) = confirm("r\n\n.\n..\nnul\0.avi\nrenamed.avi\nn\nn\n");
+
+                assert_that(&res).is_equal_to(vec![(
+                    Path::new("tests/data/movie_1.avi"),
+                    PathBuf::from("/tmp/renamed.avi"),
+                )]);
+                assert_that(&output).contains("File name must not be empty.");
+                assert_that(&output).contains("File name must not be '.' or '..'.");
+                assert_that(&output).contains("File name must not contain NUL characters.");
+                assert_that(&output.matches("New file name: ").count()).is_equal_to(5);
+            }
+
+            #[test]
+            fn rename_at_end_of_input_quits() {
+                let (res, _) = confirm("y\nr\n");
+
+                assert_that(&res).has_length(1);
+            }
+
+            #[test]
+            fn invalid_answer_asks_again() {
+                let (res, output) = confirm("x\ny\n");
+
+                assert_that(&res).has_length(1);
+                assert_that(&output.matches("[y]es, [n]o").count()).is_equal_to(3);
+            }
+
+            #[test]
+            fn shows_size_and_collision() {
+                let mut output = Vec::new();
+                let res = {
+                    let mut prompt = Prompt::new("y\n".as_bytes(), &mut output);
+                    prompt.ask_for_move(
+                        Path::new("tests/data/movie_1.avi"),
+                        Path::new("tests/data/movie_2.mp4"),
+                    )
+                };
+                let output = String::from_utf8(output).unwrap();
+
+                assert_that(&res).is_ok().is_equal_to(Answer::Yes);
+                assert_that(&output).starts_with(
+                    "Move tests/data/movie_1.avi (10) to tests/data/movie_2.mp4, overwriting existing file (10)?",
+                );
+            }
+        }
+
+        mod archive {
+            use super::*;
+
+            #[test]
+            fn kind_from_path() {
+                assert_that(&ArchiveKind::from_path(Path::new("a/movie.zip")))
+                    .is_equal_to(Some(ArchiveKind::Zip));
+                assert_that(&ArchiveKind::from_path(Path::new("a/movie.tar")))
+                    .is_equal_to(Some(ArchiveKind::Tar));
+                assert_that(&ArchiveKind::from_path(Path::new("a/movie.tgz")))
+                    .is_equal_to(Some(ArchiveKind::TarGz));
+                assert_that(&ArchiveKind::from_path(Path::new("a/Movie.RAR")))
+                    .is_equal_to(Some(ArchiveKind::Rar));
+                assert_that(&ArchiveKind::from_path(Path::new("a/movie.part01.rar")))
+                    .is_equal_to(Some(ArchiveKind::Rar));
+                assert_that(&ArchiveKind::from_path(Path::new("a/movie.part02.rar"))).is_none();
+                assert_that(&ArchiveKind::from_path(Path::new("a/movie.r00"))).is_none();
+                assert_that(&ArchiveKind::from_path(Path::new("a/movie.mkv"))).is_none();
+            }
+
+            #[test]
+            fn archive_volume_new_style() {
+                assert_that(&is_archive_volume("movie.part1.rar", "movie.part1.rar")).is_true();
+                assert_that(&is_archive_volume("movie.part1.rar", "movie.part2.rar")).is_true();
+                assert_that(&is_archive_volume("movie.part1.rar", "other.part2.rar")).is_false();
+                assert_that(&is_archive_volume("movie.part1.rar", "movie.mkv")).is_false();
+            }
+
+            #[test]
+            fn archive_volume_old_style() {
+                assert_that(&is_archive_volume("movie.rar", "movie.r00")).is_true();
+                assert_that(&is_archive_volume("movie.rar", "movie.s01")).is_true();
+                assert_that(&is_archive_volume("movie.rar", "movie.nfo")).is_false();
+                assert_that(&is_archive_volume("movie.rar", "movie.2.r00")).is_false();
+                assert_that(&is_archive_volume("movie.zip", "movie.r00")).is_false();
+            }
+
+            #[test]
+            fn unrar_listing() {
+                let listing = r#"
+Archive: movie.part1.rar
+Details: RAR 5, volume
+
+        Name: Sample
+        Type: Directory
+
+        Name: Sample/movie-sample.mkv
+        Type: File
+        Size: 1024
+
+        Name: movie.mkv
+        Type: File
+        Size: 734003200
+ Packed size: 104857600
+"#;
+                let expected = vec![
+                    ArchiveEntry {
+                        name: "Sample/movie-sample.mkv".to_owned(),
+                        size: 1024,
+                    },
+                    ArchiveEntry {
+                        name: "movie.mkv".to_owned(),
+                        size: 734003200,
+                    },
+                ];
+
+                let res = parse_unrar_listing(listing);
+
+                assert_that(&res).is_equal_to(expected);
+            }
+
+            #[test]
+            fn select_entries() {
+                let entries = vec![
+                    ArchiveEntry {
+                        name: "Sample/movie-sample.mkv".to_owned(),
+                        size: 1024,
+                    },
+                    ArchiveEntry {
+                        name: "movie.nfo".to_owned(),
+                        size: 4096,
+                    },
+                    ArchiveEntry {
+                        name: "movie.mkv".to_owned(),
+                        size: 4096,
+                    },
+                ];
+
+                let res = select_archive_entries(entries, &["mkv"], 2048);
+
+                assert_that(&res).is_equal_to(vec![ArchiveEntry {
+                    name: "movie.mkv".to_owned(),
+                    size: 4096,
+                }]);
+            }
+
+            #[test]
+            fn list_and_extract_zip() {
+                let dir = TempDir::new("mv_files-zip");
+                let archive = dir.join("movie.zip");
+                {
+                    let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+                    let options = zip::write::FileOptions::default();
+                    zip.start_file("movie/movie.mkv", options).unwrap();
+                    zip.write_all(b"Test data.").unwrap();
+                    zip.start_file("movie/movie.nfo", options).unwrap();
+                    zip.write_all(b"Info").unwrap();
+                    zip.finish().unwrap();
+                }
+
+                let entries = list_archive(&archive, ArchiveKind::Zip).unwrap();
+                let entries = select_archive_entries(entries, &["mkv"], 0);
+                let res = extract_archive(
+                    &archive,
+                    ArchiveKind::Zip,
+                    &entries,
+                    &dir,
+                    &CopyOptions::default(),
+                );
+                let content = fs::read_to_string(dir.join("movie.mkv"));
+
+                assert_that(&res)
+                    .is_ok()
+                    .is_equal_to(vec![dir.join("movie.mkv")]);
+                assert_that(&content)
+                    .is_ok()
+                    .is_equal_to("Test data.".to_owned());
+            }
+
+            #[test]
+            fn list_and_extract_tar_gz() {
+                let dir = TempDir::new("mv_files-tar");
+                let archive = dir.join("movie.tar.gz");
+                {
+                    let gz = flate2::write::GzEncoder::new(
+                        File::create(&archive).unwrap(),
+                        flate2::Compression::default(),
+                    );
+                    let mut tar = tar::Builder::new(gz);
+                    for (name, data) in &[("movie/movie.mkv", "Test data."), ("movie.nfo", "Info")]
+                    {
+                        let mut header = tar::Header::new_gnu();
+                        header.set_size(data.len() as u64);
+                        header.set_mode(0o640);
+                        header.set_mtime(1_000_000_000);
+                        header.set_cksum();
+                        tar.append_data(&mut header, name, data.as_bytes()).unwrap();
+                    }
+                    tar.into_inner().unwrap().finish().unwrap();
+                }
+
+                let entries = list_archive(&archive, ArchiveKind::TarGz).unwrap();
+                let entries = select_archive_entries(entries, &["mkv"], 0);
+                let options = CopyOptions {
+                    bwlimit: None,
+                    preserve: Preserve::all(),
+                };
+                let res = extract_archive(&archive, ArchiveKind::TarGz, &entries, &dir, &options);
+                let content = fs::read_to_string(dir.join("movie.mkv"));
+                let metadata = fs::metadata(dir.join("movie.mkv")).unwrap();
+                let again = extract_archive(&archive, ArchiveKind::TarGz, &entries, &dir, &options);
+                let content_again = fs::read_to_string(dir.join("movie.mkv"));
+
+                assert_that(&res)
+                    .is_ok()
+                    .is_equal_to(vec![dir.join("movie.mkv")]);
+                assert_that(&content)
+                    .is_ok()
+                    .is_equal_to("Test data.".to_owned());
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    assert_that(&(metadata.permissions().mode() & 0o7777)).is_equal_to(0o640);
+                }
+                assert_that(&FileTime::from_last_modification_time(&metadata))
+                    .is_equal_to(FileTime::from_unix_time(1_000_000_000, 0));
+                assert_that(&again).is_err();
+                assert_that(&content_again)
+                    .is_ok()
+                    .is_equal_to("Test data.".to_owned());
+            }
+
+            #[test]
+            fn volumes() {
+                let dir = TempDir::new("mv_files-volumes");
+                for name in &[
+                    "movie.rar",
+                    "movie.r00",
+                    "movie.r01",
+                    "movie.nfo",
+                    "other.rar",
+                ] {
+                    fs::write(dir.join(name), b"").unwrap();
+                }
+
+                let res = archive_volumes(&dir.join("movie.rar"));
+
+                assert_that(&res).is_ok().is_equal_to(vec![
+                    dir.join("movie.r00"),
+                    dir.join("movie.r01"),
+                    dir.join("movie.rar"),
+                ]);
+            }
+        }
+
+        #[cfg(unix)]
+        mod preserve {
+            use super::*;
+            use std::os::unix::fs::PermissionsExt;
+
+            const ORIGIN_URL: &str = "user.xdg.origin.url";
+            const FOREIGN_ID: u32 = 12345;
+
+            fn copy_with(name: &str, preserve: Preserve) -> (TempDir, PathBuf, PathBuf) {
+                let dir = TempDir::new(&format!("mv_files-preserve-{}", name));
+                let from = dir.join("from.mkv");
+                let to = dir.join("to.mkv");
+
+                fs::write(&from, b"Test data.").unwrap();
+                xattr::set(&from, ORIGIN_URL, b"https://example.com/movie.mkv").unwrap();
+                // Only root may give away files; otherwise the file keeps our ids.
+                let _ = std::os::unix::fs::chown(&from, Some(FOREIGN_ID), Some(FOREIGN_ID));
+                fs::set_permissions(&from, fs::Permissions::from_mode(0o640)).unwrap();
+                filetime::set_file_times(
+                    &from,
+                    FileTime::from_unix_time(1_000_000_000, 0),
+                    FileTime::from_unix_time(1_100_000_000, 0),
+                )
+                .unwrap();
+
+                let options = CopyOptions {
+                    preserve,
+                    ..Default::default()
+                };
+                copy_file(&from, &to, &options, |_| {}).unwrap();
+
+                (dir, from, to)
+            }
+
+            #[test]
+            fn parse_preserve_list() {
+                assert_that(&"all".parse::<Preserve>())
+                    .is_ok()
+                    .is_equal_to(Preserve::all());
+                assert_that(&"none".parse::<Preserve>())
+                    .is_ok()
+                    .is_equal_to(Preserve::default());
+                assert_that(&"timestamps,mode".parse::<Preserve>())
+                    .is_ok()
+                    .is_equal_to(Preserve {
+                        timestamps: true,
+                        mode: true,
+                        ..Default::default()
+                    });
+                assert_that(&"mode,acl".parse::<Preserve>()).is_err();
+            }
+
+            #[test]
+            fn timestamps() {
+                let preserve = "timestamps".parse().unwrap();
+                let (_dir, _, to) = copy_with("timestamps", preserve);
+                let metadata = to.metadata().unwrap();
+
+                assert_that(&FileTime::from_last_access_time(&metadata))
+                    .is_equal_to(FileTime::from_unix_time(1_000_000_000, 0));
+                assert_that(&FileTime::from_last_modification_time(&metadata))
+                    .is_equal_to(FileTime::from_unix_time(1_100_000_000, 0));
+            }
+
+            #[test]
+            fn mode() {
+                let preserve = "mode".parse().unwrap();
+                let (_dir, _, to) = copy_with("mode", preserve);
+                let metadata = to.metadata().unwrap();
+
+                assert_that(&(metadata.permissions().mode() & 0o777)).is_equal_to(0o640);
+            }
+
+            #[test]
+            fn ownership() {
+                use std::os::unix::fs::MetadataExt;
+
+                let preserve = "ownership".parse().unwrap();
+                let (_dir, _, to) = copy_with("ownership", preserve);
+                let to = to.metadata().unwrap();
+
+                let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+                if uid == 0 {
+                    assert_that(&to.uid()).is_equal_to(FOREIGN_ID);
+                    assert_that(&to.gid()).is_equal_to(FOREIGN_ID);
+                } else {
+                    assert_that(&to.uid()).is_equal_to(uid);
+                    assert_that(&to.gid()).is_equal_to(gid);
+                }
+            }
+
+            #[test]
+            fn xattr() {
+                let preserve = "xattr".parse().unwrap();
+                let (_dir, _, to) = copy_with("xattr", preserve);
+                let res = xattr::get(&to, ORIGIN_URL);
+
+                assert_that(&res)
+                    .is_ok()
+                    .is_equal_to(Some(b"https://example.com/movie.mkv".to_vec()));
+            }
+
+            #[test]
+            fn unsettable_xattrs() {
+                let unsupported = io::Error::from_raw_os_error(libc::ENOTSUP);
+                let privileged = io::Error::from_raw_os_error(libc::EPERM);
+                let io_error = io::Error::from_raw_os_error(libc::EIO);
+
+                assert_that(&is_unsettable_xattr(&unsupported)).is_true();
+                assert_that(&is_unsettable_xattr(&privileged)).is_true();
+                assert_that(&is_unsettable_xattr(&io_error)).is_false();
+            }
+
+            #[test]
+            fn failed_copy_removes_destination() {
+                let dir = TempDir::new("mv_files-preserve-failed");
+                fs::create_dir(dir.join("from.mkv")).unwrap();
+                let to = dir.join("to.mkv");
+
+                // Reading a directory fails after the destination has been created.
+                let res = copy_file(&dir.join("from.mkv"), &to, &CopyOptions::default(), |_| {});
+                let exists = to.exists();
+
+                assert_that(&res).is_err();
+                assert_that(&exists).is_false();
+            }
+
+            #[test]
+            fn failed_copy_keeps_existing_destination() {
+                let dir = TempDir::new("mv_files-preserve-existing");
+                fs::create_dir(dir.join("from.mkv")).unwrap();
+                let to = dir.join("to.mkv");
+                fs::write(&to, b"existing").unwrap();
+
+                let res = copy_file(&dir.join("from.mkv"), &to, &CopyOptions::default(), |_| {});
+                let content = fs::read(&to);
+                let files = fs::read_dir(&dir).unwrap().count();
+
+                assert_that(&res).is_err();
+                assert_that(&content.unwrap()).is_equal_to(b"existing".to_vec());
+                assert_that(&files).is_equal_to(2);
+            }
+
+            #[test]
+            fn copy_replaces_existing_destination() {
+                let dir = TempDir::new("mv_files-preserve-replace");
+                fs::write(dir.join("from.mkv"), b"new").unwrap();
+                let to = dir.join("to.mkv");
+                fs::write(&to, b"existing").unwrap();
+
+                let res = copy_file(&dir.join("from.mkv"), &to, &CopyOptions::default(), |_| {});
+                let content = fs::read(&to);
+                let files = fs::read_dir(&dir).unwrap().count();
+
+                assert_that(&res).is_ok().is_equal_to(3);
+                assert_that(&content.unwrap()).is_equal_to(b"new".to_vec());
+                assert_that(&files).is_equal_to(2);
+            }
+
+            #[test]
+            fn nothing() {
+                let (_dir, _, to) = copy_with("nothing", Preserve::default());
+                let metadata = to.metadata().unwrap();
+                let origin_url = xattr::get(&to, ORIGIN_URL);
+
+                assert_that(&FileTime::from_last_modification_time(&metadata))
+                    .is_not_equal_to(FileTime::from_unix_time(1_100_000_000, 0));
+                assert_that(&origin_url).is_ok().is_none();
+            }
+        }
+
+        mod token_bucket {
+            use super::*;
+
+            #[test]
+            fn within_burst() {
+                let now = Instant::now();
+                let mut bucket = TokenBucket::new(1000, now);
+
+                let res = bucket.consume(1000, now);
+
+                assert_that(&res).is_equal_to(Duration::from_secs(0));
+            }
+
+            #[test]
+            fn exceeding_burst() {
+                let now = Instant::now();
+                let mut bucket = TokenBucket::new(1000, now);
+
+                let res = bucket.consume(1500, now);
+
+                assert_that(&res).is_equal_to(Duration::from_millis(500));
+            }
+
+            #[test]
+            fn refill() {
+                let now = Instant::now();
+                let mut bucket = TokenBucket::new(1000, now);
+                let _ = bucket.consume(1000, now);
+
+                let res = bucket.consume(500, now + Duration::from_millis(500));
+
+                assert_that(&res).is_equal_to(Duration::from_secs(0));
+            }
+
+            #[test]
+            fn refill_does_not_exceed_burst() {
+                let now = Instant::now();
+                let mut bucket = TokenBucket::new(1000, now);
+
+                let res = bucket.consume(2000, now + Duration::from_secs(10));
+
+                assert_that(&res).is_equal_to(Duration::from_secs(1));
+            }
+        }
+
+        mod transfer_rate {
+            use super::*;
+
+            #[test]
+            fn current_and_average() {
+                let now = Instant::now();
+                let mut rate = TransferRate::new(now);
+
+                let first = rate.update(1000, now + Duration::from_millis(500));
+                let second = rate.update(1000, now + Duration::from_secs(1));
+                let third = rate.update(3000, now + Duration::from_secs(2));
+
+                assert_that(&first).is_false();
+                assert_that(&second).is_true();
+                assert_that(&third).is_true();
+                assert_that(&rate.total()).is_equal_to(5000);
+                assert_that(&rate.current()).is_equal_to(3000f64);
+                assert_that(&rate.average()).is_equal_to(2500f64);
+            }
+        }
+
+        mod copy_with_limit {
+            use super::*;
+
+            #[test]
+            fn unlimited() {
+                let src = vec![42u8; 3 * COPY_BUFFER_SIZE + 1];
+                let mut dest = Vec::new();
+                let mut chunks = 0;
+
+                let res = copy_with_limit(&mut src.as_slice(), &mut dest, None, |_| chunks += 1);
+
+                assert_that(&res).is_ok().is_equal_to(src.len() as u64);
+                assert_that(&chunks).is_equal_to(4);
+                assert_that(&dest).is_equal_to(src);
+            }
+
+            #[test]
+            fn limited() {
+                let src = vec![42u8; 2 * COPY_BUFFER_SIZE];
+                let mut dest = Vec::new();
+                let start = Instant::now();
+
+                let res = copy_with_limit(
+                    &mut src.as_slice(),
+                    &mut dest,
+                    Some(COPY_BUFFER_SIZE as u64 * 10),
+                    |_| {},
+                );
+
+                assert_that(&res).is_ok().is_equal_to(src.len() as u64);
+                // Burst covers the first 10 chunks, so there's no wait.
+                assert_that(&start.elapsed()).is_less_than(Duration::from_millis(100));
+                assert_that(&dest).is_equal_to(src);
+            }
+
+            #[test]
+            fn throttled() {
+                let src = vec![42u8; 12 * COPY_BUFFER_SIZE];
+                let mut dest = Vec::new();
+                let start = Instant::now();
+
+                let res = copy_with_limit(
+                    &mut src.as_slice(),
+                    &mut dest,
+                    Some(COPY_BUFFER_SIZE as u64 * 10),
+                    |_| {},
+                );
+
+                assert_that(&res).is_ok().is_equal_to(src.len() as u64);
+                // Burst covers 10 chunks, the remaining 2 chunks take 200 ms at 10 chunks/s.
+                assert_that(&start.elapsed())
+                    .is_greater_than_or_equal_to(Duration::from_millis(150));
+                assert_that(&dest).is_equal_to(src);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_util {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// An empty temporary directory which is removed when dropped; leftovers of earlier runs
+    /// are removed first.
+    pub struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub fn new(name: &str) -> TempDir {
+            let dir =
+                std::env::temp_dir().join(format!("clams-bin-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).expect("Failed to create temporary directory");
+            TempDir(dir)
+        }
+    }
+
+    impl std::ops::Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
         }
     }
 }
@@ -0,0 +1,217 @@
+use super::*;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "avif", "bmp", "gif", "jpeg", "jpg", "png", "svg", "tif", "tiff", "webp",
+];
+
+/// A file to copy into a page bundle.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Asset {
+    /// File name within the page bundle
+    pub name: String,
+    pub is_image: bool,
+    #[serde(skip)]
+    pub source: PathBuf,
+}
+
+/// Checks that all `sources` are files with distinct names that don't clash with the note
+/// `note_file_name`.
+pub fn check_assets<P: AsRef<Path>>(
+    sources: &[P],
+    note_file_name: &str,
+) -> Result<Vec<Asset>, NnError> {
+    let mut assets: Vec<Asset> = Vec::new();
+    for source in sources {
+        let source = source.as_ref();
+        let invalid = |reason: &str| NnError::InvalidAsset {
+            arg: format!("'{}' {}", source.display(), reason),
+        };
+
+        if !source.is_file() {
+            return Err(invalid("is not a file"));
+        }
+        let name = source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| invalid("has no valid file name"))?
+            .to_string();
+        if name == note_file_name || assets.iter().any(|a| a.name == name) {
+            return Err(invalid(&format!(
+                "clashes with another file named '{}'",
+                name
+            )));
+        }
+        let is_image = source
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+
+        assets.push(Asset {
+            name,
+            is_image,
+            source: source.to_path_buf(),
+        });
+    }
+
+    Ok(assets)
+}
+
+/// Whether `path` is the note of a page bundle, i.e., `index.md` or `_index.md`.
+pub fn is_page_bundle(path: &Path) -> bool {
+    path.file_stem()
+        .is_some_and(|stem| stem == "index" || stem == "_index")
+}
+
+/// Turns `dir/slug.md` into the page bundle `dir/slug/index.md`; paths that already are page
+/// bundles are kept.
+pub fn bundle_path(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_stem(), path.extension()) {
+        _ if is_page_bundle(path) => path.to_path_buf(),
+        (Some(dir), Some(stem), extension) => {
+            let mut bundle = dir.join(stem).join("index");
+            if let Some(extension) = extension {
+                bundle.set_extension(extension);
+            }
+            bundle
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Copies `assets` into `dir` without overwriting any existing file. If copying fails, assets
+/// copied so far are removed again.
+pub fn copy_assets(assets: &[Asset], dir: &Path) -> Result<(), NnError> {
+    let mut copied = Vec::new();
+    for asset in assets {
+        let to = dir.join(&asset.name);
+        let res = File::open(&asset.source).and_then(|mut from| {
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&to)?;
+            copied.push(to.clone());
+            std::io::copy(&mut from, &mut file)?;
+            file.sync_all()
+        });
+
+        if let Err(e) = res {
+            for path in &copied {
+                let _ = fs::remove_file(path);
+            }
+            return Err(NnError::FailedToCopyAsset {
+                arg: format!("failed to copy '{}' because {}", asset.source.display(), e),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+
+    #[test]
+    fn bundle_paths() {
+        assert_that(&bundle_path(Path::new("notes/2020/hello.md")))
+            .is_equal_to(PathBuf::from("notes/2020/hello/index.md"));
+        assert_that(&bundle_path(Path::new("notes/hello/index.md")))
+            .is_equal_to(PathBuf::from("notes/hello/index.md"));
+        assert_that(&bundle_path(Path::new("notes/hello/_index.md")))
+            .is_equal_to(PathBuf::from("notes/hello/_index.md"));
+    }
+
+    #[test]
+    fn check_and_copy() {
+        let src = TempDir::new("new_note-assets-src");
+        let bundle = TempDir::new("new_note-assets-bundle");
+        fs::write(src.join("diagram.SVG"), "<svg/>").unwrap();
+        fs::write(src.join("data.csv"), "a,b").unwrap();
+        let sources = vec![src.join("diagram.SVG"), src.join("data.csv")];
+
+        let assets = check_assets(&sources, "index.md");
+        assert_that(&assets).is_ok();
+        let assets = assets.unwrap();
+        let res = copy_assets(&assets, &bundle);
+
+        assert_that(&assets.iter().map(|a| a.is_image).collect::<Vec<_>>())
+            .is_equal_to(vec![true, false]);
+        assert_that(&res).is_ok();
+        assert_that(&fs::read_to_string(bundle.join("data.csv")).unwrap())
+            .is_equal_to("a,b".to_owned());
+    }
+
+    #[test]
+    fn missing_asset() {
+        let res = check_assets(&[Path::new("/does/not/exist.png")], "index.md");
+
+        assert_that(&res).is_err();
+    }
+
+    #[test]
+    fn clashing_assets() {
+        let src = TempDir::new("new_note-assets-clash");
+        fs::create_dir_all(src.join("other")).unwrap();
+        fs::write(src.join("img.png"), "").unwrap();
+        fs::write(src.join("other/img.png"), "").unwrap();
+        fs::write(src.join("index.md"), "").unwrap();
+
+        let duplicate = check_assets(
+            &[src.join("img.png"), src.join("other/img.png")],
+            "index.md",
+        );
+        let note = check_assets(&[src.join("index.md")], "index.md");
+
+        assert_that(&duplicate).is_err();
+        assert_that(&note).is_err();
+    }
+
+    #[test]
+    fn copy_does_not_overwrite_and_rolls_back() {
+        let src = TempDir::new("new_note-assets-rollback-src");
+        let bundle = TempDir::new("new_note-assets-rollback-bundle");
+        fs::write(src.join("a.png"), "new").unwrap();
+        fs::write(src.join("b.png"), "new").unwrap();
+        fs::write(bundle.join("b.png"), "old").unwrap();
+        let assets = check_assets(&[src.join("a.png"), src.join("b.png")], "index.md").unwrap();
+
+        let res = copy_assets(&assets, &bundle);
+
+        assert_that(&res).is_err();
+        assert_that(&bundle.join("a.png").exists()).is_false();
+        assert_that(&fs::read_to_string(bundle.join("b.png")).unwrap())
+            .is_equal_to("old".to_owned());
+    }
+
+    #[test]
+    fn assets_in_template() {
+        let assets = vec![
+            Asset {
+                name: "a.png".to_owned(),
+                is_image: true,
+                source: PathBuf::new(),
+            },
+            Asset {
+                name: "b.csv".to_owned(),
+                is_image: false,
+                source: PathBuf::new(),
+            },
+        ];
+        let frontmatter = FrontMatter::new("Title", "2020-06-10");
+        let context = TemplateContext::new(&frontmatter, "title").with_assets(&assets);
+        let template = NoteTemplate::new(
+            "{{#each assets}}{{#if is_image}}![]({{name}})\n{{else}}[{{name}}]({{name}})\n{{/if}}{{/each}}",
+        );
+
+        let res = render_template(&template, &context);
+
+        assert_that(&res)
+            .is_ok()
+            .is_equal_to("![](a.png)\n[b.csv](b.csv)\n".to_owned());
+    }
+}
@@ -0,0 +1,509 @@
+use super::*;
+use clams::config::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Config, Debug, Serialize, Deserialize)]
+pub struct NewNoteConfig {
+    pub notes_directory: String,
+    /// Template for notes without kinds and for kinds without their own template
+    pub notes_template: Option<String>,
+    /// Template file or directory used instead of `notes_template` -- cf. `NoteTemplate::load`
+    pub notes_template_file: Option<String>,
+    /// Language used to transliterate titles into slugs; defaults to "de"
+    pub slug_language: Option<String>,
+    /// Maximum length of slugs; defaults to 80
+    pub slug_max_length: Option<usize>,
+    /// Path of notes relative to the notes directory -- cf. `note_path`; defaults to
+    /// "{date}/{slug}.md"
+    pub path_pattern: Option<String>,
+    /// Format of `{{frontmatter_block}}`; defaults to YAML
+    pub frontmatter_format: Option<FrontMatterFormat>,
+    /// Fields every rendered note must contain, e.g., ["title", "date"]; defaults to none, so
+    /// templates without frontmatter work, but a frontmatter is always checked for syntax
+    pub required_fields: Option<Vec<String>>,
+    /// Timezone of publication dates, e.g., "Europe/Berlin", "UTC", or "+02:00"; defaults to
+    /// the machine's timezone
+    pub timezone: Option<String>,
+    /// strftime format of the frontmatter field date; defaults to "%Y-%m-%d", e.g.,
+    /// "%Y-%m-%dT%H:%M:%S%:z" for RFC 3339
+    pub date_format: Option<String>,
+    /// Directory of drafts created by `--draft` relative to `notes_directory`; defaults to
+    /// "drafts"
+    pub drafts_directory: Option<String>,
+    #[serde(default)]
+    pub kinds: BTreeMap<String, NoteKindConfig>,
+    #[serde(default)]
+    pub journal: JournalConfig,
+    #[serde(default)]
+    pub git: GitConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+}
+
+/// Shell commands run in `notes_directory` around the creation of a note; cf. `run_hook`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// Runs before the note is created; if it fails, the note is not created
+    pub pre_create: Option<String>,
+    /// Runs after the note has been created and edited; failures are reported only
+    pub post_create: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GitConfig {
+    /// Template of the commit message of `--commit`; defaults to "note: {{title}}"
+    pub commit_message: Option<String>,
+    /// Template of the branch name of `--branch`; defaults to "draft/{{slug}}"
+    pub branch: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitOptions {
+    pub commit_message: String,
+    pub branch: String,
+}
+
+impl Default for GitOptions {
+    fn default() -> Self {
+        GitOptions {
+            commit_message: "note: {{title}}".to_string(),
+            branch: "draft/{{slug}}".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct JournalConfig {
+    /// Kind of journal notes; defaults to the default kind
+    pub kind: Option<String>,
+    /// strftime format of the title of journal notes; defaults to "Journal %Y-%m-%d"
+    pub title: Option<String>,
+    /// Heading journal entries are appended under; defaults to "## Journal"
+    pub heading: Option<String>,
+    /// strftime format of the timestamp of each entry; defaults to "%H:%M"
+    pub time_format: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalOptions {
+    pub kind: Option<String>,
+    pub title: String,
+    pub heading: String,
+    pub time_format: String,
+}
+
+impl Default for JournalOptions {
+    fn default() -> Self {
+        JournalOptions {
+            kind: None,
+            title: "Journal %Y-%m-%d".to_string(),
+            heading: "## Journal".to_string(),
+            time_format: "%H:%M".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NoteKindConfig {
+    /// Use this kind if no kind has been selected
+    #[serde(default)]
+    pub default: bool,
+    pub template: Option<String>,
+    /// Template file or directory used instead of `template` -- cf. `NoteTemplate::load`
+    pub template_file: Option<String>,
+    /// Directory relative to `notes_directory`
+    pub directory: Option<String>,
+    pub path_pattern: Option<String>,
+    pub frontmatter_format: Option<FrontMatterFormat>,
+    pub required_fields: Option<Vec<String>>,
+    /// Default frontmatter fields
+    #[serde(default)]
+    pub fields: BTreeMap<String, FrontMatterType>,
+}
+
+/// Where the template of a kind comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateSource {
+    /// The template itself
+    Inline(String),
+    /// A template file or a directory of templates
+    File(PathBuf),
+}
+
+impl TemplateSource {
+    /// Returns the source configured by either `template` or `template_file`; `keys` names
+    /// both settings for the error if both are set.
+    fn from_config(
+        template: Option<&String>,
+        template_file: Option<&String>,
+        keys: &str,
+    ) -> Result<Option<TemplateSource>, NnError> {
+        match (template, template_file) {
+            (Some(_), Some(_)) => Err(NnError::InvalidNoteKind {
+                arg: format!("only one of {} may be set", keys),
+            }),
+            (Some(template), None) => Ok(Some(TemplateSource::Inline(template.to_string()))),
+            (None, Some(file)) => Ok(Some(TemplateSource::File(PathBuf::from(file)))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// A kind of note with all settings resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteKind {
+    pub name: String,
+    pub template: TemplateSource,
+    pub directory: PathBuf,
+    pub path_pattern: String,
+    pub frontmatter_format: FrontMatterFormat,
+    pub required_fields: Vec<String>,
+    pub fields: BTreeMap<String, FrontMatterType>,
+}
+
+impl NewNoteConfig {
+    pub fn slug_options(&self) -> SlugOptions {
+        let default = SlugOptions::default();
+        SlugOptions {
+            language: self.slug_language.clone().unwrap_or(default.language),
+            max_length: self.slug_max_length.unwrap_or(default.max_length),
+        }
+    }
+
+    pub fn date_format(&self) -> &str {
+        self.date_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT)
+    }
+
+    pub fn journal_options(&self) -> JournalOptions {
+        let default = JournalOptions::default();
+        let journal = &self.journal;
+        JournalOptions {
+            kind: journal.kind.clone(),
+            title: journal.title.clone().unwrap_or(default.title),
+            heading: journal.heading.clone().unwrap_or(default.heading),
+            time_format: journal.time_format.clone().unwrap_or(default.time_format),
+        }
+    }
+
+    pub fn git_options(&self) -> GitOptions {
+        let default = GitOptions::default();
+        GitOptions {
+            commit_message: self
+                .git
+                .commit_message
+                .clone()
+                .unwrap_or(default.commit_message),
+            branch: self.git.branch.clone().unwrap_or(default.branch),
+        }
+    }
+
+    pub fn drafts_directory(&self) -> PathBuf {
+        PathBuf::from(&self.notes_directory).join(
+            self.drafts_directory
+                .as_deref()
+                .unwrap_or(DEFAULT_DRAFTS_DIRECTORY),
+        )
+    }
+
+    /// Returns `notes_directory` and all kind and drafts directories outside of it.
+    pub fn note_directories(&self) -> Vec<PathBuf> {
+        let notes_directory = PathBuf::from(&self.notes_directory);
+        let mut directories = vec![notes_directory.clone()];
+        let kind_directories = self
+            .kinds
+            .values()
+            .filter_map(|kind| kind.directory.as_ref())
+            .map(|dir| notes_directory.join(dir));
+        for dir in kind_directories.chain(Some(self.drafts_directory())) {
+            if !directories.iter().any(|d| dir.starts_with(d)) {
+                directories.push(dir);
+            }
+        }
+
+        directories
+    }
+
+    /// Resolves the kind `name` or the default kind. Without any kinds, configs with just
+    /// `notes_template` yield the kind "note".
+    pub fn kind(&self, name: Option<&str>) -> Result<NoteKind, NnError> {
+        let legacy = NoteKindConfig::default();
+        let (name, kind_config) = match name {
+            Some(name) => match self.kinds.get(name) {
+                Some(kind_config) => (name, kind_config),
+                None => {
+                    let kinds: Vec<_> = self.kinds.keys().map(|k| k.as_str()).collect();
+                    return Err(NnError::InvalidNoteKind {
+                        arg: format!(
+                            "'{}' does not exist; available kinds are [{}]",
+                            name,
+                            kinds.join(", ")
+                        ),
+                    });
+                }
+            },
+            None => self.default_kind()?.unwrap_or(("note", &legacy)),
+        };
+
+        let kind_template = TemplateSource::from_config(
+            kind_config.template.as_ref(),
+            kind_config.template_file.as_ref(),
+            &format!("template and template_file of '{}'", name),
+        )?;
+        let template = match kind_template {
+            Some(template) => template,
+            None => self
+                .notes_template()?
+                .ok_or_else(|| NnError::InvalidNoteKind {
+                    arg: format!("'{}' has no template and there's no notes_template", name),
+                })?,
+        };
+        let mut directory = PathBuf::from(&self.notes_directory);
+        if let Some(ref dir) = kind_config.directory {
+            directory.push(dir);
+        }
+
+        let path_pattern = kind_config
+            .path_pattern
+            .as_ref()
+            .or(self.path_pattern.as_ref())
+            .map(|p| p.as_str())
+            .unwrap_or(DEFAULT_PATH_PATTERN);
+
+        Ok(NoteKind {
+            name: name.to_string(),
+            template,
+            directory,
+            path_pattern: path_pattern.to_string(),
+            frontmatter_format: kind_config
+                .frontmatter_format
+                .or(self.frontmatter_format)
+                .unwrap_or_default(),
+            required_fields: kind_config
+                .required_fields
+                .as_ref()
+                .or(self.required_fields.as_ref())
+                .cloned()
+                .unwrap_or_default(),
+            fields: kind_config.fields.clone(),
+        })
+    }
+
+    fn notes_template(&self) -> Result<Option<TemplateSource>, NnError> {
+        TemplateSource::from_config(
+            self.notes_template.as_ref(),
+            self.notes_template_file.as_ref(),
+            "notes_template and notes_template_file",
+        )
+    }
+
+    /// Returns `None` for legacy configs without kinds.
+    fn default_kind(&self) -> Result<Option<(&str, &NoteKindConfig)>, NnError> {
+        let defaults: Vec<_> = self.kinds.iter().filter(|(_, k)| k.default).collect();
+
+        match defaults.len() {
+            1 => Ok(Some((defaults[0].0, defaults[0].1))),
+            0 if self.kinds.len() == 1 => {
+                let (name, kind) = self.kinds.iter().next().unwrap(); // Safe, because len == 1
+                Ok(Some((name, kind)))
+            }
+            0 if self.notes_template.is_some() || self.notes_template_file.is_some() => Ok(None),
+            0 => Err(NnError::InvalidNoteKind {
+                arg: "no kind is marked as default".to_string(),
+            }),
+            _ => Err(NnError::InvalidNoteKind {
+                arg: "more than one kind is marked as default".to_string(),
+            }),
+        }
+    }
+}
+
+pub const DEFAULT_PATH_PATTERN: &str = "{date}/{slug}.md";
+
+pub const DEFAULT_DRAFTS_DIRECTORY: &str = "drafts";
+
+/// Path of drafts relative to the drafts directory; drafts get their date when published.
+pub const DRAFT_PATH_PATTERN: &str = "{slug}.md";
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use spectral::prelude::*;
+
+    const KINDS: &str = r#"
+notes_directory = "/notes"
+notes_template = "{{title}}"
+path_pattern = "{date}-{slug}.md"
+frontmatter_format = "toml"
+
+[kinds.post]
+default = true
+directory = "posts"
+fields = { categories = ["Blog"], author = "lukas" }
+
+[kinds.til]
+template = "TIL {{title}}"
+directory = "/til"
+path_pattern = "{year}/{slug}.md"
+frontmatter_format = "json"
+required_fields = ["title"]
+"#;
+
+    fn config(toml: &str) -> NewNoteConfig {
+        toml::from_str(toml).expect("Failed to parse config")
+    }
+
+    #[test]
+    fn legacy_config() {
+        let config = config("notes_directory = \"/notes\"\nnotes_template = \"{{title}}\"");
+        let expected = NoteKind {
+            name: "note".to_owned(),
+            template: TemplateSource::Inline("{{title}}".to_owned()),
+            directory: PathBuf::from("/notes"),
+            path_pattern: DEFAULT_PATH_PATTERN.to_owned(),
+            frontmatter_format: FrontMatterFormat::Yaml,
+            required_fields: Vec::new(),
+            fields: BTreeMap::new(),
+        };
+
+        let res = config.kind(None);
+
+        assert_that(&res).is_ok().is_equal_to(expected);
+    }
+
+    #[test]
+    fn default_kind() {
+        let mut fields = BTreeMap::new();
+        fields.insert(
+            "categories".to_owned(),
+            FrontMatterType::List(vec!["Blog".to_owned()]),
+        );
+        fields.insert(
+            "author".to_owned(),
+            FrontMatterType::Value("lukas".to_owned()),
+        );
+        let expected = NoteKind {
+            name: "post".to_owned(),
+            template: TemplateSource::Inline("{{title}}".to_owned()),
+            directory: PathBuf::from("/notes/posts"),
+            path_pattern: "{date}-{slug}.md".to_owned(),
+            frontmatter_format: FrontMatterFormat::Toml,
+            required_fields: Vec::new(),
+            fields,
+        };
+
+        let res = config(KINDS).kind(None);
+
+        assert_that(&res).is_ok().is_equal_to(expected);
+    }
+
+    #[test]
+    fn named_kind() {
+        let expected = NoteKind {
+            name: "til".to_owned(),
+            template: TemplateSource::Inline("TIL {{title}}".to_owned()),
+            directory: PathBuf::from("/til"),
+            path_pattern: "{year}/{slug}.md".to_owned(),
+            frontmatter_format: FrontMatterFormat::Json,
+            required_fields: vec!["title".to_owned()],
+            fields: BTreeMap::new(),
+        };
+
+        let res = config(KINDS).kind(Some("til"));
+
+        assert_that(&res).is_ok().is_equal_to(expected);
+    }
+
+    #[test]
+    fn template_files() {
+        let config = config(
+            "notes_directory = \"/notes\"\nnotes_template_file = \"templates\"\n[kinds.post]\ndefault = true\n[kinds.til]\ntemplate_file = \"til.hbs\"",
+        );
+
+        let post = config.kind(None).map(|k| k.template);
+        let til = config.kind(Some("til")).map(|k| k.template);
+
+        assert_that(&post)
+            .is_ok()
+            .is_equal_to(TemplateSource::File(PathBuf::from("templates")));
+        assert_that(&til)
+            .is_ok()
+            .is_equal_to(TemplateSource::File(PathBuf::from("til.hbs")));
+    }
+
+    #[test]
+    fn template_and_template_file() {
+        let config = config(
+            "notes_directory = \"/notes\"\n[kinds.til]\ntemplate = \"TIL\"\ntemplate_file = \"til.hbs\"",
+        );
+
+        let res = config.kind(None);
+
+        assert_that(&res).is_err();
+    }
+
+    #[test]
+    fn legacy_config_with_template_file() {
+        let config = config("notes_directory = \"/notes\"\nnotes_template_file = \"note.hbs\"");
+
+        let res = config.kind(None).map(|k| k.template);
+
+        assert_that(&res)
+            .is_ok()
+            .is_equal_to(TemplateSource::File(PathBuf::from("note.hbs")));
+    }
+
+    #[test]
+    fn unknown_kind() {
+        let res = config(KINDS).kind(Some("meeting"));
+
+        assert_that(&res).is_err();
+    }
+
+    #[test]
+    fn single_kind_is_default() {
+        let config = config("notes_directory = \"/notes\"\n[kinds.til]\ntemplate = \"TIL\"");
+
+        let res = config.kind(None).map(|k| k.name);
+
+        assert_that(&res).is_ok().is_equal_to("til".to_owned());
+    }
+
+    #[test]
+    fn ambiguous_default_kind() {
+        let config = config(
+            "notes_directory = \"/notes\"\n[kinds.a]\ndefault = true\n[kinds.b]\ndefault = true",
+        );
+
+        let res = config.kind(None);
+
+        assert_that(&res).is_err();
+    }
+
+    #[test]
+    fn note_directories() {
+        let res = config(KINDS).note_directories();
+
+        assert_that(&res).is_equal_to(vec![PathBuf::from("/notes"), PathBuf::from("/til")]);
+    }
+
+    #[test]
+    fn drafts_directory_outside_notes_directory() {
+        let config = config("notes_directory = \"/notes\"\ndrafts_directory = \"/drafts\"");
+
+        let res = config.note_directories();
+
+        assert_that(&config.drafts_directory()).is_equal_to(PathBuf::from("/drafts"));
+        assert_that(&res).is_equal_to(vec![PathBuf::from("/notes"), PathBuf::from("/drafts")]);
+    }
+
+    #[test]
+    fn kind_without_template() {
+        let config = config("notes_directory = \"/notes\"\n[kinds.til]\ndefault = true");
+
+        let res = config.kind(None);
+
+        assert_that(&res).is_err();
+    }
+}
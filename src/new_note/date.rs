@@ -0,0 +1,430 @@
+use super::*;
+use chrono::prelude::*;
+use chrono::Duration;
+use serde::Serialize;
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::path::PathBuf;
+
+const DATE_FORMATS: &str = "'now', 'YYYY-MM-DD HH:MM', 'YYYY-MM-DD', RFC 3339, \
+     '+3d' or '-2w' with units h, d, w, and 'today', 'tomorrow', 'yesterday', 'monday', or \
+     'next monday' optionally followed by 'HH:MM'";
+
+pub fn str_date_to_date(date: &str) -> Result<DateTime<Local>, NnError> {
+    parse_date(date, Local::now())
+}
+
+/// Parses absolute as well as relative dates; relative dates are relative to `now`. Relative
+/// days keep the time of `now` unless a time is given, e.g., `tomorrow 09:00`. A weekday means
+/// the next such day including today while `next <weekday>` excludes today.
+pub fn parse_date(date: &str, now: DateTime<Local>) -> Result<DateTime<Local>, NnError> {
+    let date = date.trim();
+    let invalid = || NnError::FailedToParsePublicationDate {
+        arg: format!("'{}' is none of {}", date, DATE_FORMATS),
+    };
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(date) {
+        return Ok(dt.with_timezone(&Local));
+    }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M") {
+        return naive_to_local(dt).ok_or_else(invalid);
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        return naive_to_local(d.and_hms(0, 0, 0)).ok_or_else(invalid);
+    }
+    if let Some(offset) = parse_offset(date) {
+        return now.checked_add_signed(offset).ok_or_else(invalid);
+    }
+
+    let lower = date.to_lowercase();
+    let mut words: Vec<_> = lower.split_whitespace().collect();
+    let time = match words.last().map(|w| NaiveTime::parse_from_str(w, "%H:%M")) {
+        Some(Ok(time)) => {
+            words.pop();
+            time
+        }
+        _ => now.time(),
+    };
+    let today = now.naive_local().date();
+    let day = match words.as_slice() {
+        ["now"] if time == now.time() => return Ok(now),
+        ["today"] => today,
+        ["tomorrow"] => today.succ(),
+        ["yesterday"] => today.pred(),
+        [weekday] => next_weekday(today, weekday.parse().map_err(|_| invalid())?, false),
+        ["next", weekday] => next_weekday(today, weekday.parse().map_err(|_| invalid())?, true),
+        _ => return Err(invalid()),
+    };
+
+    naive_to_local(day.and_time(time)).ok_or_else(invalid)
+}
+
+/// Parses `+3d`, `-2w`, etc.; offsets too large for `Duration` yield `None`, since chrono's
+/// constructors panic on them.
+fn parse_offset(offset: &str) -> Option<Duration> {
+    let sign = match offset.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let unit = offset.chars().last()?;
+    let amount: i64 = offset.get(1..offset.len() - 1)?.parse().ok()?;
+    let millis_per_unit: i64 = match unit {
+        'h' => 3_600_000,
+        'd' => 86_400_000,
+        'w' => 604_800_000,
+        _ => return None,
+    };
+
+    amount
+        .checked_mul(sign * millis_per_unit)
+        .map(Duration::milliseconds)
+}
+
+fn next_weekday(from: NaiveDate, weekday: Weekday, exclude_today: bool) -> NaiveDate {
+    let mut day = if exclude_today { from.succ() } else { from };
+    while day.weekday() != weekday {
+        day = day.succ();
+    }
+    day
+}
+
+pub(super) fn naive_to_local(dt: NaiveDateTime) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(&dt).single()
+}
+
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Translates `timezone` into a value for the environment variable `TZ` which determines the
+/// timezone of `Local`; `None` means the machine's timezone. Timezones are either "local",
+/// "UTC", fixed offsets like "+02:00" or "UTC-5", or names of the tz database like
+/// "Europe/Berlin".
+pub fn timezone_to_tz_env(timezone: &str) -> Result<Option<String>, NnError> {
+    let timezone = timezone.trim();
+    let invalid = |reason: &str| NnError::InvalidTimezone {
+        arg: format!("'{}' {}", timezone, reason),
+    };
+
+    match timezone.to_lowercase().as_str() {
+        "" | "local" => return Ok(None),
+        "utc" | "gmt" | "z" => return Ok(Some("UTC0".to_string())),
+        _ => {}
+    }
+
+    let offset = ["UTC", "GMT", "utc", "gmt"]
+        .iter()
+        .find_map(|prefix| timezone.strip_prefix(prefix))
+        .unwrap_or(timezone);
+    if offset.starts_with(['+', '-']) {
+        let (sign, posix_sign) = if offset.starts_with('+') {
+            ('+', '-')
+        } else {
+            ('-', '+')
+        };
+        let digits: String = offset[1..].chars().filter(|c| *c != ':').collect();
+        let (hours, minutes) = match digits.len() {
+            1 | 2 => (digits.parse::<u32>().ok(), Some(0)),
+            4 => (digits[..2].parse().ok(), digits[2..].parse().ok()),
+            _ => (None, None),
+        };
+        return match (hours, minutes) {
+            (Some(h), Some(m))
+                if digits.chars().all(|c| c.is_ascii_digit()) && h <= 14 && m < 60 =>
+            {
+                Ok(Some(format!(
+                    "<{}{:02}{:02}>{}{:02}:{:02}",
+                    sign, h, m, posix_sign, h, m
+                )))
+            }
+            _ => Err(invalid("is not an offset like +02:00")),
+        };
+    }
+
+    // Names are looked up as paths, so they must not leave the tz database.
+    if timezone.starts_with('/') || timezone.contains("..") {
+        return Err(invalid("is not a timezone name"));
+    }
+    let zoneinfo = env::var_os("TZDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/usr/share/zoneinfo"));
+    if !zoneinfo.join(timezone).is_file() {
+        return Err(invalid(&format!(
+            "is not in the tz database '{}'",
+            zoneinfo.display()
+        )));
+    }
+
+    Ok(Some(timezone.to_string()))
+}
+
+/// Publication date in various formats for templates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PublicationDate {
+    /// `YYYY-MM-DD`
+    pub day: String,
+    /// `HH:MM`
+    pub time: String,
+    /// RFC 3339 including the offset
+    pub rfc3339: String,
+    /// Offset like `+02:00`
+    pub offset: String,
+    /// Unix timestamp
+    pub timestamp: i64,
+}
+
+impl PublicationDate {
+    pub fn new(date: &DateTime<Local>) -> PublicationDate {
+        PublicationDate {
+            day: date_to_iso_day(date),
+            time: date.format("%H:%M").to_string(),
+            rfc3339: date.to_rfc3339_opts(SecondsFormat::Secs, false),
+            offset: date.format("%:z").to_string(),
+            timestamp: date.timestamp(),
+        }
+    }
+}
+
+pub fn date_to_iso_day(dt: &DateTime<Local>) -> String {
+    dt.format("%Y-%m-%d").to_string()
+}
+
+pub fn date_to_iso_time(dt: &DateTime<Local>) -> String {
+    dt.format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Parses dates as used in frontmatters, i.e., `%Y-%m-%d`, `%Y-%m-%d %H:%M`, or RFC 3339.
+pub(super) fn parse_frontmatter_date(date: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|dt| dt.naive_local())
+        .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M"))
+        .or_else(|_| NaiveDate::parse_from_str(date, "%Y-%m-%d").map(|d| d.and_hms(0, 0, 0)))
+        .ok()
+}
+
+/// Formats `date` with the strftime `format` without panicking on invalid formats.
+pub fn format_datetime(date: &DateTime<Local>, format: &str) -> Result<String, NnError> {
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(format)).map_err(|_| NnError::InvalidDateFormat {
+        arg: format.to_string(),
+    })?;
+
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use spectral::prelude::*;
+
+    #[test]
+    fn str_date_to_date_okay() {
+        let str_date = "2001-01-01 01:01";
+        let expected = Local.ymd(2001, 01, 01).and_hms(01, 01, 00);
+
+        let res = str_date_to_date(str_date);
+
+        assert_that(&res).is_ok().is_equal_to(expected);
+    }
+
+    mod parse_date {
+        use super::*;
+
+        /// Wednesday
+        fn now() -> DateTime<Local> {
+            Local.ymd(2020, 06, 10).and_hms(12, 30, 00)
+        }
+
+        fn parse(date: &str) -> Result<DateTime<Local>, NnError> {
+            parse_date(date, now())
+        }
+
+        #[test]
+        fn now_() {
+            assert_that(&parse("now")).is_ok().is_equal_to(now());
+        }
+
+        #[test]
+        fn date_time() {
+            let expected = Local.ymd(2001, 01, 01).and_hms(01, 01, 00);
+
+            assert_that(&parse("2001-01-01 01:01"))
+                .is_ok()
+                .is_equal_to(expected);
+        }
+
+        #[test]
+        fn date_only() {
+            let expected = Local.ymd(2026, 10, 20).and_hms(00, 00, 00);
+
+            assert_that(&parse("2026-10-20"))
+                .is_ok()
+                .is_equal_to(expected);
+        }
+
+        #[test]
+        fn rfc3339() {
+            let expected = Utc.ymd(2026, 10, 20).and_hms(07, 00, 00);
+
+            let res = parse("2026-10-20T09:00:00+02:00").map(|dt| dt.with_timezone(&Utc));
+
+            assert_that(&res).is_ok().is_equal_to(expected);
+        }
+
+        #[test]
+        fn offsets() {
+            assert_that(&parse("+3d"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 13).and_hms(12, 30, 00));
+            assert_that(&parse("-1w"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 03).and_hms(12, 30, 00));
+            assert_that(&parse("+2h"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 10).and_hms(14, 30, 00));
+        }
+
+        #[test]
+        fn out_of_range_offsets() {
+            assert_that(&parse("+99999999999999d")).is_err();
+            assert_that(&parse("-99999999999999w")).is_err();
+            assert_that(&parse("+9999999999h")).is_err();
+        }
+
+        #[test]
+        fn relative_days() {
+            assert_that(&parse("today 08:00"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 10).and_hms(08, 00, 00));
+            assert_that(&parse("Tomorrow"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 11).and_hms(12, 30, 00));
+            assert_that(&parse("yesterday"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 09).and_hms(12, 30, 00));
+        }
+
+        #[test]
+        fn weekdays() {
+            assert_that(&parse("wednesday"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 10).and_hms(12, 30, 00));
+            assert_that(&parse("next wednesday"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 17).and_hms(12, 30, 00));
+            assert_that(&parse("next monday 09:00"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 15).and_hms(09, 00, 00));
+            assert_that(&parse("fri"))
+                .is_ok()
+                .is_equal_to(Local.ymd(2020, 06, 12).and_hms(12, 30, 00));
+        }
+
+        #[test]
+        fn invalid() {
+            let res = parse("someday");
+
+            assert_that(&res).is_err();
+            assert_that(&res.unwrap_err().to_string()).contains("YYYY-MM-DD");
+        }
+
+        #[test]
+        fn invalid_offset() {
+            assert_that(&parse("+3y")).is_err();
+            assert_that(&parse("+d")).is_err();
+        }
+    }
+
+    mod timezone {
+        use super::*;
+
+        fn tz(timezone: &str) -> Result<Option<String>, NnError> {
+            timezone_to_tz_env(timezone)
+        }
+
+        #[test]
+        fn local_and_utc() {
+            assert_that(&tz("local")).is_ok().is_none();
+            assert_that(&tz("UTC"))
+                .is_ok()
+                .is_equal_to(Some("UTC0".to_owned()));
+        }
+
+        #[test]
+        fn offsets() {
+            assert_that(&tz("+02:00"))
+                .is_ok()
+                .is_equal_to(Some("<+0200>-02:00".to_owned()));
+            assert_that(&tz("-0530"))
+                .is_ok()
+                .is_equal_to(Some("<-0530>+05:30".to_owned()));
+            assert_that(&tz("UTC+2"))
+                .is_ok()
+                .is_equal_to(Some("<+0200>-02:00".to_owned()));
+        }
+
+        #[test]
+        fn invalid_offsets() {
+            assert_that(&tz("+25:00")).is_err();
+            assert_that(&tz("+02:60")).is_err();
+            assert_that(&tz("+2:0")).is_err();
+            assert_that(&tz("+ab")).is_err();
+        }
+
+        #[test]
+        fn invalid_names() {
+            assert_that(&tz("Nowhere/Atlantis")).is_err();
+            assert_that(&tz("../../etc/passwd")).is_err();
+            assert_that(&tz("/etc/localtime")).is_err();
+            assert_that(&tz("Europe/..")).is_err();
+        }
+
+        #[test]
+        fn publication_date() {
+            let date = Local.ymd(2026, 10, 20).and_hms(09, 30, 00);
+            let offset = date.format("%:z").to_string();
+
+            let res = PublicationDate::new(&date);
+
+            assert_that(&res.day.as_str()).is_equal_to("2026-10-20");
+            assert_that(&res.time.as_str()).is_equal_to("09:30");
+            assert_that(&res.rfc3339).is_equal_to(format!("2026-10-20T09:30:00{}", offset));
+            assert_that(&res.timestamp).is_equal_to(date.timestamp());
+            assert_that(&res.offset).is_equal_to(offset);
+        }
+
+        #[test]
+        fn publication_date_in_template() {
+            let date = Local.ymd(2026, 10, 20).and_hms(09, 30, 00);
+            let frontmatter = FrontMatter::new("Title", "2026-10-20");
+            let context = TemplateContext::new(&frontmatter, "title").with_publication_date(&date);
+            let template =
+                NoteTemplate::new("{{published.day}} {{published.time}} {{published.timestamp}}");
+
+            let res = render_template(&template, &context);
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to(format!("2026-10-20 09:30 {}", date.timestamp()));
+        }
+    }
+
+    #[test]
+    fn date_to_iso_day_okay() {
+        let date = Local.ymd(2001, 01, 01).and_hms(01, 01, 00);
+        let expected = "2001-01-01".to_owned();
+
+        let res = date_to_iso_day(&date);
+
+        assert_that(&res).is_equal_to(expected);
+    }
+
+    #[test]
+    fn date_to_iso_time_okay() {
+        let date = Local.ymd(2001, 01, 01).and_hms(01, 01, 00);
+        let expected = "2001-01-01 01:01".to_owned();
+
+        let res = date_to_iso_time(&date);
+
+        assert_that(&res).is_equal_to(expected);
+    }
+}
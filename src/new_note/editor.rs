@@ -0,0 +1,170 @@
+use super::*;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Opens `file` in the editor from `$VISUAL` or `$EDITOR` and waits for the editor to exit.
+pub fn open_editor(file: &Path) -> Result<(), NnError> {
+    let visual = env::var("VISUAL").ok();
+    let editor = env::var("EDITOR").ok();
+    let mut command = editor_command(visual.as_deref(), editor.as_deref())?;
+    let program = command.remove(0);
+
+    let status = Command::new(&program)
+        .args(command)
+        .arg(file.as_os_str())
+        .status()
+        .map_err(|e| NnError::FailedToExecEditor {
+            arg: format!("'{}' failed to start: {}", program, e),
+        })?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(NnError::EditorFailed {
+            arg: format!("'{}' {}", program, status),
+        })
+    }
+}
+
+/// Selects the editor command like git does: `$VISUAL` before `$EDITOR` before `vi`.
+pub fn editor_command(visual: Option<&str>, editor: Option<&str>) -> Result<Vec<String>, NnError> {
+    let command = visual
+        .into_iter()
+        .chain(editor)
+        .find(|c| !c.trim().is_empty())
+        .unwrap_or("vi");
+    let words = split_command(command)?;
+    if words.is_empty() {
+        return Err(NnError::FailedToExecEditor {
+            arg: "editor command is empty".to_string(),
+        });
+    }
+
+    Ok(words)
+}
+
+/// Splits `command` into words like a POSIX shell, i.e., honouring single quotes, double
+/// quotes, and backslash escapes, but without any expansions.
+pub fn split_command(command: &str) -> Result<Vec<String>, NnError> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(w) = word.take() {
+                    words.push(w);
+                }
+            }
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => w.push(c),
+                        None => return Err(unterminated_quote(command)),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if c == '"' || c == '\\' || c == '$' || c == '`' => w.push(c),
+                            Some(c) => {
+                                w.push('\\');
+                                w.push(c);
+                            }
+                            None => return Err(unterminated_quote(command)),
+                        },
+                        Some(c) => w.push(c),
+                        None => return Err(unterminated_quote(command)),
+                    }
+                }
+            }
+            '\\' => {
+                let w = word.get_or_insert_with(String::new);
+                if let Some(c) = chars.next() {
+                    w.push(c);
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(w) = word {
+        words.push(w);
+    }
+
+    Ok(words)
+}
+
+fn unterminated_quote(command: &str) -> NnError {
+    NnError::FailedToExecEditor {
+        arg: format!("'{}' contains an unterminated quote", command),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use spectral::prelude::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn visual_before_editor() {
+        let res = editor_command(Some("code -w"), Some("vim"));
+
+        assert_that(&res)
+            .is_ok()
+            .is_equal_to(words(&["code", "-w"]));
+    }
+
+    #[test]
+    fn editor_without_visual() {
+        let res = editor_command(None, Some("emacsclient -t"));
+
+        assert_that(&res)
+            .is_ok()
+            .is_equal_to(words(&["emacsclient", "-t"]));
+    }
+
+    #[test]
+    fn empty_visual_falls_back() {
+        let res = editor_command(Some(" "), None);
+
+        assert_that(&res).is_ok().is_equal_to(words(&["vi"]));
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        let res = split_command(r#"'/Applications/Sublime Text/subl' --wait "a \"b\" \c" d\ e''f"#);
+
+        assert_that(&res).is_ok().is_equal_to(words(&[
+            "/Applications/Sublime Text/subl",
+            "--wait",
+            "a \"b\" \\c",
+            "d ef",
+        ]));
+    }
+
+    #[test]
+    fn empty_quotes_are_a_word() {
+        let res = split_command("vim ''");
+
+        assert_that(&res).is_ok().is_equal_to(words(&["vim", ""]));
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        let res = split_command("vim 'note");
+
+        assert_that(&res).is_err();
+    }
+}
@@ -0,0 +1,197 @@
+use super::*;
+use std::fs;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Writes `content` to `path` without ever overwriting an existing file. The content is written
+/// to a temporary file first which is then hard linked to `path`, so there's either a complete
+/// note or none at all. On file systems without hard links, `path` is created exclusively
+/// instead.
+pub fn write_content_to_file(content: &str, path: &Path) -> Result<(), NnError> {
+    let write_err = |e: std::io::Error| NnError::FailedToWriteNoteFile { arg: e.to_string() };
+
+    // Make sure, the destnation dir and all its parents exist.
+    let dir = path
+        .parent()
+        .ok_or_else(|| NnError::FailedToWriteNoteFile {
+            arg: "path does not contain directory".to_string(),
+        })?;
+    fs::create_dir_all(dir).map_err(write_err)?;
+    if path.exists() {
+        return Err(NnError::NoteAlreadyExists {
+            arg: path.display().to_string(),
+        });
+    }
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| NnError::FailedToWriteNoteFile {
+            arg: "path does not contain file name".to_string(),
+        })?;
+    // Only `path` existing may be reported as `AlreadyExists` here; stale temporary files are
+    // skipped by `write_tmp_file`.
+    let tmp_path = write_tmp_file(content, dir, file_name).map_err(write_err)?;
+    let res = fs::hard_link(&tmp_path, path);
+    let _ = fs::remove_file(&tmp_path);
+    let res = match res {
+        Err(ref e) if is_hard_link_unsupported(e) => write_new_file(content, path),
+        res => res,
+    };
+
+    res.map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => NnError::NoteAlreadyExists {
+            arg: path.display().to_string(),
+        },
+        _ => write_err(e),
+    })
+}
+
+/// Atomically replaces the content of the existing note at `path` by writing `content` to a
+/// temporary file first which then replaces `path`.
+pub fn replace_note_content(content: &str, path: &Path) -> Result<(), NnError> {
+    let write_err = |e: std::io::Error| NnError::FailedToWriteNoteFile { arg: e.to_string() };
+
+    let (dir, file_name) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(file_name)) => (dir, file_name),
+        _ => {
+            return Err(NnError::FailedToWriteNoteFile {
+                arg: format!("'{}' is not a file path", path.display()),
+            })
+        }
+    };
+    let tmp_path = write_tmp_file(content, dir, file_name).map_err(write_err)?;
+    let res = fs::metadata(path).and_then(|metadata| {
+        fs::set_permissions(&tmp_path, metadata.permissions())?;
+        fs::rename(&tmp_path, path)
+    });
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    res.map_err(write_err)
+}
+
+const MAX_TMP_FILE_ATTEMPTS: usize = 100;
+
+/// Writes `content` to a new temporary file next to `file_name` in `dir` and returns its path.
+/// Existing files, e.g., left behind by a crashed process with the same pid, are never touched;
+/// the next free name is used instead.
+fn write_tmp_file(
+    content: &str,
+    dir: &Path,
+    file_name: &std::ffi::OsStr,
+) -> std::io::Result<PathBuf> {
+    for attempt in 0..MAX_TMP_FILE_ATTEMPTS {
+        let tmp_path = dir.join(tmp_file_name(file_name, attempt));
+        match write_new_file(content, &tmp_path) {
+            Ok(()) => return Ok(tmp_path),
+            Err(ref e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => {
+                let _ = fs::remove_file(&tmp_path);
+                return Err(e);
+            }
+        }
+    }
+
+    Err(std::io::Error::other(
+        "failed to find an unused temporary file name",
+    ))
+}
+
+fn tmp_file_name(file_name: &std::ffi::OsStr, attempt: usize) -> String {
+    let pid = std::process::id();
+    match attempt {
+        0 => format!(".{}.{}.tmp", file_name.to_string_lossy(), pid),
+        _ => format!(".{}.{}-{}.tmp", file_name.to_string_lossy(), pid, attempt),
+    }
+}
+
+/// Whether `e` means that the file system does not support hard links, as opposed to, e.g., a
+/// full disk.
+fn is_hard_link_unsupported(e: &std::io::Error) -> bool {
+    [
+        libc::EPERM,
+        libc::ENOTSUP,
+        libc::EOPNOTSUPP,
+        libc::EXDEV,
+        libc::ENOSYS,
+    ]
+    .iter()
+    .any(|code| e.raw_os_error() == Some(*code))
+}
+
+/// Creates `path` exclusively and writes `content`; a partially written file is removed.
+fn write_new_file(content: &str, path: &Path) -> std::io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    let res = file
+        .write_all(content.as_bytes())
+        .and_then(|_| file.sync_all());
+    if res.is_err() {
+        let _ = fs::remove_file(path);
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn write_content_to_file_does_not_overwrite() {
+        let dir = TempDir::new("new_note-overwrite");
+        let path = dir.join("hello.md");
+
+        let first = write_content_to_file("First", &path);
+        let second = write_content_to_file("Second", &path);
+
+        assert_that(&first).is_ok();
+        assert_that(&second).is_err();
+        assert_that(&matches!(second, Err(NnError::NoteAlreadyExists { .. }))).is_true();
+        assert_that(&fs::read_to_string(&path).unwrap()).is_equal_to("First".to_owned());
+        assert_that(&fs::read_dir(&dir).unwrap().count()).is_equal_to(1);
+    }
+
+    #[test]
+    fn write_content_to_file_skips_stale_tmp_files() {
+        let dir = TempDir::new("new_note-stale");
+        let path = dir.join("hello.md");
+        let stale = dir.join(tmp_file_name(OsStr::new("hello.md"), 0));
+        write_content_to_file("Stale", &stale).unwrap();
+
+        let res = write_content_to_file("Hello", &path);
+
+        assert_that(&res).is_ok();
+        assert_that(&fs::read_to_string(&path).unwrap()).is_equal_to("Hello".to_owned());
+        assert_that(&fs::read_to_string(&stale).unwrap()).is_equal_to("Stale".to_owned());
+        assert_that(&fs::read_dir(&dir).unwrap().count()).is_equal_to(2);
+    }
+
+    #[test]
+    fn only_unsupported_hard_links_fall_back() {
+        let error = std::io::Error::from_raw_os_error;
+
+        assert_that(&is_hard_link_unsupported(&error(libc::EXDEV))).is_true();
+        assert_that(&is_hard_link_unsupported(&error(libc::EPERM))).is_true();
+        assert_that(&is_hard_link_unsupported(&error(libc::ENOSPC))).is_false();
+        assert_that(&is_hard_link_unsupported(&error(libc::EIO))).is_false();
+        assert_that(&is_hard_link_unsupported(&error(libc::EACCES))).is_false();
+    }
+
+    #[test]
+    fn write_content_to_file_creates_directories() {
+        let dir = TempDir::new("new_note-write");
+        let path = dir.join("2020/06/hello/index.md");
+
+        let res = write_content_to_file("Hello", &path);
+
+        assert_that(&res).is_ok();
+        assert_that(&fs::read_to_string(&path).unwrap()).is_equal_to("Hello".to_owned());
+    }
+}
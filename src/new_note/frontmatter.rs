@@ -0,0 +1,1238 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FrontMatterType {
+    Value(String),
+    List(Vec<String>),
+    /// Any other value, e.g., a boolean, a number, or a nested table, keeping its type
+    Typed(serde_json::Value),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct FrontMatter {
+    pub fields: BTreeMap<String, FrontMatterType>,
+}
+
+impl FrontMatter {
+    pub fn new(title: &str, date: &str) -> FrontMatter {
+        let mut frontmatter = FrontMatter::default();
+        frontmatter.set_value("title", title);
+        frontmatter.set_value("date", date);
+        frontmatter
+    }
+
+    pub fn get_value(&self, key: &str) -> Option<&str> {
+        match self.fields.get(key) {
+            Some(FrontMatterType::Value(ref value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn set_value(&mut self, key: &str, value: &str) {
+        self.fields
+            .insert(key.to_string(), FrontMatterType::Value(value.to_string()));
+    }
+
+    pub fn set_bool(&mut self, key: &str, value: bool) {
+        self.fields.insert(
+            key.to_string(),
+            FrontMatterType::Typed(serde_json::Value::Bool(value)),
+        );
+    }
+
+    /// Adds `value` to the list `key`; a single value becomes the first element of the list.
+    pub fn add_to_list(&mut self, key: &str, value: &str) {
+        let list = match self.fields.remove(key) {
+            Some(FrontMatterType::List(mut list)) => {
+                list.push(value.to_string());
+                list
+            }
+            Some(FrontMatterType::Value(first)) => vec![first, value.to_string()],
+            Some(FrontMatterType::Typed(_)) | None => vec![value.to_string()],
+        };
+        self.fields
+            .insert(key.to_string(), FrontMatterType::List(list));
+    }
+
+    /// Whether `draft` is `true`, either as a boolean or as a string.
+    pub fn is_draft(&self) -> bool {
+        match self.fields.get("draft") {
+            Some(FrontMatterType::Value(value)) => value == "true",
+            Some(FrontMatterType::Typed(value)) => value.as_bool() == Some(true),
+            _ => false,
+        }
+    }
+
+    /// Sets all fields of `other` replacing existing ones.
+    pub fn merge(&mut self, other: FrontMatter) {
+        self.fields.extend(other.fields);
+    }
+
+    /// Returns the list `key`; a single value is treated as a list with one element.
+    pub fn get_list(&self, key: &str) -> Vec<&str> {
+        match self.fields.get(key) {
+            Some(FrontMatterType::List(ref list)) => list.iter().map(|v| v.as_str()).collect(),
+            Some(FrontMatterType::Value(ref value)) => vec![value],
+            Some(FrontMatterType::Typed(_)) | None => Vec::new(),
+        }
+    }
+}
+
+/// Parses `key=value` and `key=[value, value]` into a frontmatter field. Keys that are names of
+/// template variables, cf. `TEMPLATE_VARIABLES`, are rejected.
+pub fn parse_field(field: &str) -> Result<(String, FrontMatterType), NnError> {
+    let splits: Vec<_> = field.splitn(2, '=').collect();
+    let key = splits[0].trim();
+    if splits.len() != 2 || key.is_empty() || key.contains(char::is_whitespace) {
+        return Err(NnError::InvalidFrontMatterField {
+            arg: field.to_string(),
+        });
+    }
+    if TEMPLATE_VARIABLES.contains(&key) {
+        return Err(NnError::ReservedFrontMatterField {
+            arg: key.to_string(),
+        });
+    }
+
+    let value = splits[1].trim();
+    let value = if value.starts_with('[') && value.ends_with(']') {
+        let list = value[1..value.len() - 1]
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+        FrontMatterType::List(list)
+    } else {
+        FrontMatterType::Value(value.to_string())
+    };
+
+    Ok((key.to_string(), value))
+}
+
+impl FrontMatterFormat {
+    /// Detects the format of the frontmatter `content` starts with.
+    pub fn detect(content: &str) -> Option<FrontMatterFormat> {
+        let first_line = content.lines().next()?.trim_end();
+        match first_line {
+            "---" => Some(FrontMatterFormat::Yaml),
+            "+++" => Some(FrontMatterFormat::Toml),
+            _ if first_line.starts_with('{') => Some(FrontMatterFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FrontMatterFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FrontMatterFormat::Yaml => "YAML",
+            FrontMatterFormat::Toml => "TOML",
+            FrontMatterFormat::Json => "JSON",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Splits `content` into its frontmatter and body; returns `None` if `content` does not start
+/// with a frontmatter. The format is detected by the first line, i.e., `---` for YAML, `+++`
+/// for TOML, and `{` for JSON.
+pub fn parse_frontmatter(content: &str) -> Result<Option<(FrontMatter, &str)>, NnError> {
+    match FrontMatterFormat::detect(content) {
+        Some(format) => parse_frontmatter_as(content, format).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Splits `content` into its frontmatter in `format` and body. Errors refer to line numbers of
+/// `content`.
+pub fn parse_frontmatter_as(
+    content: &str,
+    format: FrontMatterFormat,
+) -> Result<(FrontMatter, &str), NnError> {
+    match format {
+        FrontMatterFormat::Yaml => parse_yaml_frontmatter(content),
+        FrontMatterFormat::Toml => parse_toml_frontmatter(content),
+        FrontMatterFormat::Json => parse_json_frontmatter(content),
+    }
+}
+
+fn invalid_frontmatter(line: usize, reason: &str) -> NnError {
+    NnError::InvalidFrontMatter {
+        arg: format!("line {}: {}", line, reason),
+    }
+}
+
+/// Returns the frontmatter between the delimiter lines, the line number of its first line, and
+/// the rest of `content`.
+fn delimited_frontmatter<'a>(
+    content: &'a str,
+    delimiter: &str,
+) -> Result<(&'a str, &'a str), NnError> {
+    let mut lines = content.split_inclusive('\n');
+    match lines.next() {
+        Some(first) if first.trim_end() == delimiter => {}
+        _ => {
+            return Err(invalid_frontmatter(
+                1,
+                &format!("expected opening '{}'", delimiter),
+            ))
+        }
+    }
+
+    let start = content.find('\n').map(|i| i + 1).unwrap_or(content.len());
+    let mut offset = start;
+    for line in lines {
+        if line.trim_end() == delimiter {
+            return Ok((&content[start..offset], &content[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+
+    Err(NnError::InvalidFrontMatter {
+        arg: format!("missing closing '{}'", delimiter),
+    })
+}
+
+/// Only the subset of YAML written by note templates is supported, i.e., `key: value`,
+/// `key: [value, value]`, lists of `- value` lines, `|` and `>` block scalars, and JSON flow
+/// mappings. Scalars are read as strings. Anything else, e.g., nested block mappings, as well as
+/// duplicate keys are rejected, so a frontmatter is never read differently than it's written.
+fn parse_yaml_frontmatter(content: &str) -> Result<(FrontMatter, &str), NnError> {
+    let mut lines = content.split_inclusive('\n');
+    match lines.next() {
+        Some(first) if first.trim_end() == "---" => {}
+        _ => return Err(invalid_frontmatter(1, "expected opening '---'")),
+    }
+
+    let mut frontmatter = FrontMatter::default();
+    let mut list_key: Option<String> = None;
+    let mut list_indent: Option<usize> = None;
+    let mut block: Option<(String, bool, Vec<&str>)> = None;
+    let mut offset = content.find('\n').map(|i| i + 1).unwrap_or(content.len());
+    // Line numbers refer to `content` and thus start with 2 after the opening `---`.
+    for (number, line) in lines.enumerate().map(|(i, l)| (i + 2, l)) {
+        offset += line.len();
+        let invalid = |reason: &str| invalid_frontmatter(number, reason);
+        let trimmed = line.trim();
+
+        if block.is_some() && (trimmed.is_empty() || line.starts_with(char::is_whitespace)) {
+            if let Some((_, _, ref mut block_lines)) = block {
+                block_lines.push(trimmed);
+            }
+            continue;
+        }
+        if let Some((key, folded, block_lines)) = block.take() {
+            let separator = if folded { " " } else { "\n" };
+            let value = block_lines.join(separator).trim().to_string();
+            frontmatter
+                .fields
+                .insert(key, FrontMatterType::Value(value));
+        }
+
+        if trimmed == "---" || trimmed == "..." {
+            return Ok((frontmatter, &content[offset..]));
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        if line[..indent].contains('\t') {
+            return Err(invalid("tabs are not allowed for indentation"));
+        }
+
+        if trimmed == "-" || trimmed.starts_with("- ") {
+            let key = list_key
+                .as_ref()
+                .ok_or_else(|| invalid("list item without key"))?;
+            if *list_indent.get_or_insert(indent) != indent {
+                return Err(invalid("list items must be indented equally"));
+            }
+            let item = trimmed[1..].trim_start();
+            if item.starts_with('[') || item.starts_with("- ") {
+                return Err(invalid("nested lists are not supported"));
+            }
+            let value = parse_yaml_value(item).map_err(|e| invalid(&e))?;
+            frontmatter.add_to_list(key, &value);
+        } else if indent > 0 {
+            return Err(invalid(&format!(
+                "unexpected indentation of '{}'; nested mappings are not supported",
+                trimmed
+            )));
+        } else {
+            let splits: Vec<_> = line.splitn(2, ':').collect();
+            let is_mapping = splits.len() == 2
+                && !splits[0].trim().is_empty()
+                && (splits[1].is_empty() || splits[1].starts_with(char::is_whitespace));
+            if !is_mapping || trimmed.starts_with("? ") {
+                return Err(invalid(&format!(
+                    "expected 'key: value', found '{}'",
+                    trimmed
+                )));
+            }
+            let key = parse_yaml_value(splits[0]).map_err(|e| invalid(&e))?;
+            if frontmatter.fields.contains_key(&key) {
+                return Err(invalid(&format!("duplicate key '{}'", key)));
+            }
+            let value = splits[1].trim();
+
+            list_key = None;
+            list_indent = None;
+            if value.is_empty() {
+                frontmatter
+                    .fields
+                    .insert(key.clone(), FrontMatterType::List(Vec::new()));
+                list_key = Some(key);
+            } else if value.starts_with('[') {
+                let list = parse_yaml_flow_list(value).map_err(|e| invalid(&e))?;
+                frontmatter.fields.insert(key, FrontMatterType::List(list));
+            } else if value.starts_with('{') {
+                // Flow mappings are only supported as written by `frontmatter_block`, i.e.,
+                // as JSON.
+                let value = serde_json::from_str(value).map_err(|_| {
+                    invalid(&format!(
+                        "unsupported flow mapping '{}'; quote the value",
+                        value
+                    ))
+                })?;
+                frontmatter
+                    .fields
+                    .insert(key, FrontMatterType::Typed(value));
+            } else if value.starts_with('|') || value.starts_with('>') {
+                let indicator = value.split(" #").next().unwrap_or(value).trim();
+                if !["|", ">", "|-", ">-", "|+", ">+"].contains(&indicator) {
+                    return Err(invalid(&format!("unsupported block scalar '{}'", value)));
+                }
+                block = Some((key, value.starts_with('>'), Vec::new()));
+            } else {
+                let value = parse_yaml_value(value).map_err(|e| invalid(&e))?;
+                frontmatter
+                    .fields
+                    .insert(key, FrontMatterType::Value(value));
+            }
+        }
+    }
+
+    Err(NnError::InvalidFrontMatter {
+        arg: "missing closing '---'".to_string(),
+    })
+}
+
+/// Parses a plain or quoted YAML scalar.
+pub(super) fn parse_yaml_value(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let unterminated = || format!("unterminated quote in '{}'", value);
+    let check_rest = |unquoted: String, rest: &str| {
+        let rest = rest.trim();
+        if rest.is_empty() || rest.starts_with('#') {
+            Ok(unquoted)
+        } else {
+            Err(format!(
+                "unexpected '{}' after quoted value in '{}'",
+                rest, value
+            ))
+        }
+    };
+
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut unquoted = String::new();
+        let mut chars = quoted.char_indices();
+        loop {
+            match chars.next() {
+                Some((i, '"')) => return check_rest(unquoted, &quoted[i + 1..]),
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, 'n')) => unquoted.push('\n'),
+                    Some((_, 't')) => unquoted.push('\t'),
+                    Some((_, 'r')) => unquoted.push('\r'),
+                    Some((i, 'u')) => {
+                        let c = quoted
+                            .get(i + 1..i + 5)
+                            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                            .and_then(std::char::from_u32)
+                            .ok_or_else(|| format!("invalid escape sequence in '{}'", value))?;
+                        unquoted.push(c);
+                        for _ in 0..4 {
+                            chars.next();
+                        }
+                    }
+                    Some((_, c)) => unquoted.push(c),
+                    None => return Err(unterminated()),
+                },
+                Some((_, c)) => unquoted.push(c),
+                None => return Err(unterminated()),
+            }
+        }
+    } else if let Some(quoted) = value.strip_prefix('\'') {
+        let mut unquoted = String::new();
+        let mut chars = quoted.char_indices().peekable();
+        loop {
+            match chars.next() {
+                Some((_, '\'')) if chars.peek().is_some_and(|(_, c)| *c == '\'') => {
+                    chars.next();
+                    unquoted.push('\'');
+                }
+                Some((i, '\'')) => return check_rest(unquoted, &quoted[i + 1..]),
+                Some((_, c)) => unquoted.push(c),
+                None => return Err(unterminated()),
+            }
+        }
+    } else {
+        let value = match value.find(" #") {
+            Some(comment) => &value[..comment],
+            None => value,
+        }
+        .trim();
+        let starts_sequence = value == "-" || value.starts_with("- ");
+        if starts_sequence
+            || value.starts_with(['&', '*', '!', '%', '@', '`', '{', '}', '[', ']', '|', '>'])
+        {
+            return Err(format!(
+                "unsupported or reserved indicator at the beginning of '{}'; quote the value",
+                value
+            ));
+        }
+        if value.contains(": ") || value.ends_with(':') {
+            return Err(format!("unexpected ':' in '{}'; quote the value", value));
+        }
+        Ok(value.to_string())
+    }
+}
+
+/// Parses `[value, "value", ...]`.
+fn parse_yaml_flow_list(list: &str) -> Result<Vec<String>, String> {
+    let list = list.trim();
+    if !list.ends_with(']') {
+        return Err(format!("unterminated list '{}'", list));
+    }
+
+    let mut values = Vec::new();
+    let mut value = String::new();
+    let mut quote = None;
+    for c in list[1..list.len() - 1].chars() {
+        match (quote, c) {
+            (None, ',') => {
+                values.push(value.clone());
+                value.clear();
+                continue;
+            }
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '[') | (None, ']') | (None, '{') | (None, '}') => {
+                return Err(format!("nested structures in '{}' are not supported", list))
+            }
+            (Some(q), c) if q == c => quote = None,
+            _ => {}
+        }
+        value.push(c);
+    }
+    if quote.is_some() {
+        return Err(format!("unterminated quote in '{}'", list));
+    }
+    values.push(value);
+
+    values
+        .iter()
+        .map(|v| parse_yaml_value(v))
+        .filter(|v| v.as_ref().map(|v| !v.is_empty()).unwrap_or(true))
+        .collect()
+}
+
+fn parse_toml_frontmatter(content: &str) -> Result<(FrontMatter, &str), NnError> {
+    let (toml, body) = delimited_frontmatter(content, "+++")?;
+    let table: toml::value::Table = toml::from_str(toml).map_err(|e| {
+        // Line numbers of the TOML error are 0-based and exclude the opening `+++`.
+        let line = e.line_col().map(|(line, _)| line + 2).unwrap_or(1);
+        let reason = e.to_string();
+        let reason = reason.split(" at line ").next().unwrap_or(&reason);
+        invalid_frontmatter(line, reason)
+    })?;
+
+    let mut frontmatter = FrontMatter::default();
+    for (key, value) in table {
+        let value = match toml_to_json(value) {
+            serde_json::Value::String(s) => FrontMatterType::Value(s),
+            value => json_to_frontmatter_type(value),
+        };
+        frontmatter.fields.insert(key, value);
+    }
+
+    Ok((frontmatter, body))
+}
+
+/// Converts `value` keeping its type except for dates which become strings like in YAML and
+/// JSON frontmatters.
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s),
+        toml::Value::Integer(i) => serde_json::Value::from(i),
+        toml::Value::Float(f) => serde_json::Value::from(f),
+        toml::Value::Boolean(b) => serde_json::Value::Bool(b),
+        toml::Value::Datetime(dt) => serde_json::Value::String(dt.to_string()),
+        toml::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(toml_to_json).collect())
+        }
+        toml::Value::Table(table) => serde_json::Value::Object(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, toml_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Strings and lists of strings become values and lists; everything else keeps its type.
+fn json_to_frontmatter_type(value: serde_json::Value) -> FrontMatterType {
+    match value {
+        serde_json::Value::String(s) => FrontMatterType::Value(s),
+        serde_json::Value::Array(values) if values.iter().all(|v| v.is_string()) => {
+            FrontMatterType::List(
+                values
+                    .into_iter()
+                    .filter_map(|v| match v {
+                        serde_json::Value::String(s) => Some(s),
+                        _ => None,
+                    })
+                    .collect(),
+            )
+        }
+        value => FrontMatterType::Typed(value),
+    }
+}
+
+fn parse_json_frontmatter(content: &str) -> Result<(FrontMatter, &str), NnError> {
+    let mut values = serde_json::Deserializer::from_str(content).into_iter::<serde_json::Value>();
+    let object = match values.next() {
+        Some(Ok(serde_json::Value::Object(object))) => object,
+        Some(Ok(_)) => return Err(invalid_frontmatter(1, "expected a JSON object")),
+        Some(Err(e)) => {
+            let reason = e.to_string();
+            let reason = reason.split(" at line ").next().unwrap_or(&reason);
+            return Err(invalid_frontmatter(e.line(), reason));
+        }
+        None => return Err(invalid_frontmatter(1, "expected a JSON object")),
+    };
+    let body = &content[values.byte_offset()..];
+    let body = body.strip_prefix('\n').unwrap_or(body);
+
+    let mut frontmatter = FrontMatter::default();
+    for (key, value) in object {
+        frontmatter
+            .fields
+            .insert(key, json_to_frontmatter_type(value));
+    }
+
+    Ok((frontmatter, body))
+}
+
+/// Checks that the rendered note `content` starts with a valid frontmatter in `format` that
+/// contains non-empty `required_fields`. Without required fields, notes may also have no
+/// frontmatter at all. YAML is checked by the strict subset parser, so anything outside the
+/// subset is reported as invalid rather than passed.
+pub fn validate_note(
+    content: &str,
+    format: FrontMatterFormat,
+    required_fields: &[String],
+) -> Result<(), NnError> {
+    match FrontMatterFormat::detect(content) {
+        None if required_fields.is_empty() => return Ok(()),
+        Some(detected) if detected != format => {
+            return Err(NnError::InvalidFrontMatter {
+                arg: format!(
+                    "expected {} frontmatter, but found {} frontmatter",
+                    format, detected
+                ),
+            })
+        }
+        _ => {}
+    }
+
+    let (frontmatter, _) = parse_frontmatter_as(content, format)?;
+    let missing: Vec<_> = required_fields
+        .iter()
+        .filter(|key| match frontmatter.fields.get(key.as_str()) {
+            Some(FrontMatterType::Value(value)) => value.trim().is_empty(),
+            Some(FrontMatterType::List(list)) => list.is_empty(),
+            Some(FrontMatterType::Typed(value)) => value.is_null(),
+            None => true,
+        })
+        .map(|key| key.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Err(NnError::InvalidFrontMatter {
+            arg: format!(
+                "required fields [{}] are missing or empty",
+                missing.join(", ")
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontMatterFormat {
+    /// `---` delimited YAML
+    #[default]
+    Yaml,
+    /// `+++` delimited TOML as used by Zola and Hugo
+    Toml,
+    /// A JSON object as used by Hugo
+    Json,
+}
+
+/// Serializes the frontmatter fields with `title` and `date` first and nested tables last as
+/// required by TOML.
+pub(super) struct OrderedFields<'a>(pub(super) &'a FrontMatter);
+
+impl<'a> OrderedFields<'a> {
+    fn iter(&self) -> impl Iterator<Item = (&'a String, &'a FrontMatterType)> {
+        const FIRST: [&str; 2] = ["title", "date"];
+        let fields = &self.0.fields;
+        let is_table = |value: &FrontMatterType| match value {
+            FrontMatterType::Typed(serde_json::Value::Object(_)) => true,
+            FrontMatterType::Typed(serde_json::Value::Array(values)) => {
+                !values.is_empty() && values.iter().all(|v| v.is_object())
+            }
+            _ => false,
+        };
+        let rest = move |tables: bool| {
+            fields
+                .iter()
+                .filter(move |(k, v)| !FIRST.contains(&k.as_str()) && is_table(v) == tables)
+        };
+        FIRST
+            .iter()
+            .filter_map(move |k| fields.get_key_value(*k))
+            .chain(rest(false))
+            .chain(rest(true))
+    }
+}
+
+impl<'a> Serialize for OrderedFields<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.fields.len()))?;
+        for (key, value) in self.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Renders `frontmatter` including its delimiters as `format`.
+pub fn frontmatter_block(
+    frontmatter: &FrontMatter,
+    format: FrontMatterFormat,
+) -> Result<String, NnError> {
+    let fields = OrderedFields(frontmatter);
+    let block = match format {
+        FrontMatterFormat::Yaml => yaml_frontmatter_block(&fields),
+        FrontMatterFormat::Toml => {
+            let toml = toml::to_string(&fields)
+                .map_err(|e| NnError::FailedToRenderFrontmatterTemplate { arg: e.to_string() })?;
+            format!("+++\n{}+++\n", toml)
+        }
+        FrontMatterFormat::Json => {
+            let json = serde_json::to_string_pretty(&fields)
+                .map_err(|e| NnError::FailedToRenderFrontmatterTemplate { arg: e.to_string() })?;
+            format!("{}\n", json)
+        }
+    };
+
+    Ok(block)
+}
+
+pub(super) fn yaml_frontmatter_block(fields: &OrderedFields) -> String {
+    let mut yaml = "---\n".to_string();
+    for (key, value) in fields.iter() {
+        match value {
+            FrontMatterType::Value(value) => {
+                yaml.push_str(&format!("{}: {}\n", yaml_key(key), yaml_string(value)))
+            }
+            FrontMatterType::List(list) if list.is_empty() => {
+                yaml.push_str(&format!("{}: []\n", yaml_key(key)))
+            }
+            FrontMatterType::List(list) => {
+                yaml.push_str(&format!("{}:\n", yaml_key(key)));
+                for value in list {
+                    yaml.push_str(&format!("- {}\n", yaml_string(value)));
+                }
+            }
+            // JSON is valid YAML, so typed values are written as compact JSON.
+            FrontMatterType::Typed(value) => {
+                yaml.push_str(&format!("{}: {}\n", yaml_key(key), value))
+            }
+        }
+    }
+    yaml.push_str("---\n");
+
+    yaml
+}
+
+fn yaml_key(key: &str) -> String {
+    let is_plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if is_plain {
+        key.to_string()
+    } else {
+        yaml_string(key)
+    }
+}
+
+/// Double quotes `value` which is always valid YAML.
+fn yaml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+
+    mod parse {
+        use super::*;
+
+        const NOTE: &str = r#"---
+title: "Über \"Rust\""
+date: "2020-06-10 12:30"
+description: It's a note # a comment
+link: https://example.com
+categories:
+- "Blog"
+tags: [rust, 'cli, tools', "a"]
+aliases: []
+author: {"name": "lukas", "mail": "lukas@example.com"}
+draft: true
+---
+
+Hello World!
+"#;
+
+        #[test]
+        fn parse_frontmatter_okay() {
+            let res = parse_frontmatter(NOTE);
+
+            assert_that(&res).is_ok().is_some();
+            let (frontmatter, body) = res.unwrap().unwrap();
+            assert_that(&frontmatter.get_value("title")).is_equal_to(Some("Über \"Rust\""));
+            assert_that(&frontmatter.get_value("date")).is_equal_to(Some("2020-06-10 12:30"));
+            assert_that(&frontmatter.get_value("description")).is_equal_to(Some("It's a note"));
+            assert_that(&frontmatter.get_value("link")).is_equal_to(Some("https://example.com"));
+            assert_that(&frontmatter.get_list("categories")).is_equal_to(vec!["Blog"]);
+            assert_that(&frontmatter.get_list("tags")).is_equal_to(vec!["rust", "cli, tools", "a"]);
+            assert_that(&frontmatter.get_list("aliases")).is_equal_to(Vec::<&str>::new());
+            assert_that(&frontmatter.get_value("draft")).is_equal_to(Some("true"));
+            assert_that(&frontmatter.fields.get("author")).is_equal_to(Some(
+                &FrontMatterType::Typed(
+                    serde_json::json!({"name": "lukas", "mail": "lukas@example.com"}),
+                ),
+            ));
+            assert_that(&body).is_equal_to("\nHello World!\n");
+        }
+
+        #[test]
+        fn reject_unsupported_yaml() {
+            for (content, reason) in &[
+                ("---\ntitle: A\ntitle: B\n---\n", "duplicate key 'title'"),
+                (
+                    "---\nauthor:\n  name: lukas\n---\n",
+                    "line 3: unexpected indentation",
+                ),
+                (
+                    "---\ntitle: A\n  date: B\n---\n",
+                    "line 3: unexpected indentation",
+                ),
+                ("---\ntags:\n  - a\n - b\n---\n", "indented equally"),
+                ("---\ntags:\n- - a\n---\n", "nested lists"),
+                ("---\ntitle: A\n\tdate: B\n---\n", "tabs"),
+                (
+                    "---\nlink:https://example.com\n---\n",
+                    "expected 'key: value'",
+                ),
+                ("---\ntext: |2\n   x\n---\n", "unsupported block scalar"),
+                ("---\nbase: &base x\n---\n", "reserved indicator"),
+            ] {
+                let res = parse_frontmatter(content);
+
+                assert_that(&res).is_err();
+                assert_that(&res.unwrap_err().to_string()).contains(*reason);
+            }
+        }
+
+        #[test]
+        fn parse_indented_lists() {
+            let content = "---\ntags:\n  - rust\n  - cli\n---\n";
+
+            let res = parse_frontmatter(content).unwrap().unwrap().0;
+
+            assert_that(&res.get_list("tags")).is_equal_to(vec!["rust", "cli"]);
+        }
+
+        #[test]
+        fn parse_frontmatter_roundtrip() {
+            let mut frontmatter = FrontMatter::new("Title", "2001-01-01");
+            frontmatter.add_to_list("tags", "rust");
+            frontmatter.add_to_list("tags", "cli");
+            let context = TemplateContext::new(&frontmatter, "title");
+            let template = NoteTemplate::new(
+                "---\ntitle: \"{{title}}\"\ndate: \"{{date}}\"\ntags:{{#each tags}}\n- \"{{this}}\"{{/each}}\n---\n",
+            );
+            let content = render_template(&template, &context).unwrap();
+
+            let res = parse_frontmatter(&content);
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to(Some((frontmatter, "")));
+        }
+
+        #[test]
+        fn parse_typed_toml_values() {
+            let content = "+++\ntitle = \"Title\"\ndate = 2020-06-10\nweight = 10\ndraft = true\nids = [1, 2]\n[extra]\nlang = \"en\"\n+++\n";
+
+            let (frontmatter, _) = parse_frontmatter(content).unwrap().unwrap();
+
+            assert_that(&frontmatter.get_value("date")).is_equal_to(Some("2020-06-10"));
+            assert_that(&frontmatter.fields.get("weight"))
+                .is_equal_to(Some(&FrontMatterType::Typed(serde_json::json!(10))));
+            assert_that(&frontmatter.fields.get("draft"))
+                .is_equal_to(Some(&FrontMatterType::Typed(serde_json::json!(true))));
+            assert_that(&frontmatter.fields.get("ids"))
+                .is_equal_to(Some(&FrontMatterType::Typed(serde_json::json!([1, 2]))));
+            assert_that(&frontmatter.fields.get("extra")).is_equal_to(Some(
+                &FrontMatterType::Typed(serde_json::json!({"lang": "en"})),
+            ));
+            assert_that(&frontmatter.is_draft()).is_true();
+        }
+
+        #[test]
+        fn parse_typed_json_values() {
+            let content =
+                "{\"title\": \"Title\", \"weight\": 1.5, \"params\": {\"a\": [1]}, \"x\": null}\n";
+
+            let (frontmatter, _) = parse_frontmatter(content).unwrap().unwrap();
+
+            assert_that(&frontmatter.get_value("title")).is_equal_to(Some("Title"));
+            assert_that(&frontmatter.fields.get("weight"))
+                .is_equal_to(Some(&FrontMatterType::Typed(serde_json::json!(1.5))));
+            assert_that(&frontmatter.fields.get("params"))
+                .is_equal_to(Some(&FrontMatterType::Typed(serde_json::json!({"a": [1]}))));
+            assert_that(&frontmatter.fields.get("x"))
+                .is_equal_to(Some(&FrontMatterType::Typed(serde_json::Value::Null)));
+        }
+
+        #[test]
+        fn parse_frontmatter_without_frontmatter() {
+            let res = parse_frontmatter("# Title\n");
+
+            assert_that(&res).is_ok().is_none();
+        }
+
+        #[test]
+        fn parse_frontmatter_unclosed() {
+            let res = parse_frontmatter("---\ntitle: Title\n");
+
+            assert_that(&res).is_err();
+        }
+
+        #[test]
+        fn parse_frontmatter_invalid_line() {
+            let res = parse_frontmatter("---\ntitle: Title\nno key\n---\n");
+
+            assert_that(&res.unwrap_err().to_string()).contains("line 3");
+        }
+    }
+
+    mod frontmatter {
+        use super::*;
+
+        #[test]
+        fn add_to_list() {
+            let mut frontmatter = FrontMatter::default();
+            frontmatter.set_value("tags", "rust");
+            frontmatter.add_to_list("tags", "cli");
+            frontmatter.add_to_list("categories", "Programming");
+
+            assert_that(&frontmatter.fields.get("tags")).is_equal_to(Some(&FrontMatterType::List(
+                vec!["rust".to_owned(), "cli".to_owned()],
+            )));
+            assert_that(&frontmatter.fields.get("categories"))
+                .is_equal_to(Some(&FrontMatterType::List(vec!["Programming".to_owned()])));
+        }
+
+        #[test]
+        fn parse_field_value() {
+            let res = parse_field("link = https://example.com/?a=b");
+
+            assert_that(&res).is_ok().is_equal_to((
+                "link".to_owned(),
+                FrontMatterType::Value("https://example.com/?a=b".to_owned()),
+            ));
+        }
+
+        #[test]
+        fn parse_field_list() {
+            let res = parse_field("aliases=[/old, /older,]");
+
+            assert_that(&res).is_ok().is_equal_to((
+                "aliases".to_owned(),
+                FrontMatterType::List(vec!["/old".to_owned(), "/older".to_owned()]),
+            ));
+        }
+
+        #[test]
+        fn parse_field_empty_list() {
+            let res = parse_field("aliases=[]");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to(("aliases".to_owned(), FrontMatterType::List(Vec::new())));
+        }
+
+        #[test]
+        fn parse_field_invalid() {
+            assert_that(&parse_field("no value")).is_err();
+            assert_that(&parse_field("=value")).is_err();
+            assert_that(&parse_field("white space=value")).is_err();
+        }
+
+        #[test]
+        fn parse_field_reserved() {
+            for key in TEMPLATE_VARIABLES {
+                let res = parse_field(&format!("{}=value", key));
+
+                assert_that(&matches!(
+                    res,
+                    Err(NnError::ReservedFrontMatterField { .. })
+                ))
+                .is_true();
+            }
+        }
+    }
+
+    mod frontmatter_block {
+        use super::*;
+
+        fn frontmatter() -> FrontMatter {
+            let mut frontmatter = FrontMatter::new("He said \"hi\" & left", "2020-06-10");
+            frontmatter.add_to_list("tags", "rust");
+            frontmatter.add_to_list("tags", "c:\\temp");
+            frontmatter
+                .fields
+                .insert("aliases".to_owned(), FrontMatterType::List(Vec::new()));
+            frontmatter.set_value("author name", "lukas\nmueller");
+            frontmatter
+        }
+
+        #[test]
+        fn yaml() {
+            let expected = r#"---
+title: "He said \"hi\" & left"
+date: "2020-06-10"
+aliases: []
+"author name": "lukas\nmueller"
+tags:
+- "rust"
+- "c:\\temp"
+---
+"#;
+
+            let res = frontmatter_block(&frontmatter(), FrontMatterFormat::Yaml);
+
+            assert_that(&res).is_ok().is_equal_to(expected.to_owned());
+        }
+
+        #[test]
+        fn yaml_roundtrip() {
+            let block = frontmatter_block(&frontmatter(), FrontMatterFormat::Yaml).unwrap();
+
+            let res = parse_frontmatter(&block);
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to(Some((frontmatter(), "")));
+        }
+
+        #[test]
+        fn toml() {
+            let expected = r#"+++
+title = "He said \"hi\" & left"
+date = "2020-06-10"
+aliases = []
+"author name" = "lukas\nmueller"
+tags = ["rust", "c:\\temp"]
++++
+"#;
+
+            let res = frontmatter_block(&frontmatter(), FrontMatterFormat::Toml);
+
+            assert_that(&res).is_ok().is_equal_to(expected.to_owned());
+        }
+
+        #[test]
+        fn json() {
+            let expected = r#"{
+  "title": "He said \"hi\" & left",
+  "date": "2020-06-10",
+  "aliases": [],
+  "author name": "lukas\nmueller",
+  "tags": [
+    "rust",
+    "c:\\temp"
+  ]
+}
+"#;
+
+            let res = frontmatter_block(&frontmatter(), FrontMatterFormat::Json);
+
+            assert_that(&res).is_ok().is_equal_to(expected.to_owned());
+        }
+
+        #[test]
+        fn typed_values() {
+            let mut frontmatter = FrontMatter::new("Title", "2020-06-10");
+            for (key, value) in &[
+                ("extra", serde_json::json!({"lang": "en"})),
+                ("weight", serde_json::json!(10)),
+                ("draft", serde_json::json!(true)),
+            ] {
+                frontmatter
+                    .fields
+                    .insert(key.to_string(), FrontMatterType::Typed(value.clone()));
+            }
+
+            let yaml = frontmatter_block(&frontmatter, FrontMatterFormat::Yaml).unwrap();
+            let toml = frontmatter_block(&frontmatter, FrontMatterFormat::Toml).unwrap();
+
+            assert_that(&yaml.as_str()).is_equal_to(
+                "---\ntitle: \"Title\"\ndate: \"2020-06-10\"\ndraft: true\nweight: 10\nextra: {\"lang\":\"en\"}\n---\n",
+            );
+            assert_that(&toml.as_str()).is_equal_to(
+                "+++\ntitle = \"Title\"\ndate = \"2020-06-10\"\ndraft = true\nweight = 10\n\n[extra]\nlang = \"en\"\n+++\n",
+            );
+            assert_that(&parse_frontmatter(&toml).unwrap().unwrap().0).is_equal_to(&frontmatter);
+            // Plain YAML scalars are read as strings, but nested tables are kept.
+            assert_that(
+                &parse_frontmatter(&yaml)
+                    .unwrap()
+                    .unwrap()
+                    .0
+                    .fields
+                    .get("extra"),
+            )
+            .is_equal_to(frontmatter.fields.get("extra"));
+        }
+
+        #[test]
+        fn boolean_draft() {
+            let mut frontmatter = FrontMatter::new("Title", "2020-06-10");
+            frontmatter.set_bool("draft", true);
+
+            let yaml = frontmatter_block(&frontmatter, FrontMatterFormat::Yaml);
+            let toml = frontmatter_block(&frontmatter, FrontMatterFormat::Toml);
+            let json = frontmatter_block(&frontmatter, FrontMatterFormat::Json);
+
+            assert_that(&yaml).is_ok().contains("\ndraft: true\n");
+            assert_that(&toml).is_ok().contains("\ndraft = true\n");
+            assert_that(&json).is_ok().contains("\"draft\": true\n");
+            assert_that(&frontmatter.is_draft()).is_true();
+        }
+
+        #[test]
+        fn render_without_html_escaping() {
+            let frontmatter = frontmatter();
+            let context = TemplateContext::new(&frontmatter, "slug")
+                .with_frontmatter_format(FrontMatterFormat::Json)
+                .unwrap();
+            let template = NoteTemplate::new("{{frontmatter_block}}# {{title}}");
+
+            let res = render_template(&template, &context);
+
+            assert_that(&res)
+                .is_ok()
+                .ends_with("}\n# He said \"hi\" & left");
+        }
+    }
+
+    mod validate {
+        use super::*;
+
+        fn required() -> Vec<String> {
+            vec!["title".to_owned(), "date".to_owned()]
+        }
+
+        fn validate(content: &str, format: FrontMatterFormat) -> Result<(), NnError> {
+            validate_note(content, format, &required())
+        }
+
+        #[test]
+        fn valid_formats() {
+            let yaml = "---\ntitle: \"Title\"\ndate: 2020-06-10\n---\nBody\n";
+            let toml = "+++\ntitle = \"Title\"\ndate = 2020-06-10\n+++\nBody\n";
+            let json = "{\"title\": \"Title\", \"date\": \"2020-06-10\"}\nBody\n";
+
+            assert_that(&validate(yaml, FrontMatterFormat::Yaml)).is_ok();
+            assert_that(&validate(toml, FrontMatterFormat::Toml)).is_ok();
+            assert_that(&validate(json, FrontMatterFormat::Json)).is_ok();
+        }
+
+        #[test]
+        fn yaml_unescaped_quote() {
+            let content = "---\ntitle: \"He said \"hi\"\"\ndate: 2020-06-10\n---\n";
+
+            let res = validate(content, FrontMatterFormat::Yaml);
+
+            assert_that(&res.unwrap_err().to_string()).contains("line 2");
+        }
+
+        #[test]
+        fn yaml_unquoted_colon() {
+            let content = "---\ntitle: Rust: A Review\ndate: 2020-06-10\n---\n";
+
+            let res = validate(content, FrontMatterFormat::Yaml);
+
+            assert_that(&res.unwrap_err().to_string()).contains("line 2");
+        }
+
+        #[test]
+        fn yaml_duplicate_keys() {
+            let content = "---\ntitle: Title\ndate: 2020-06-10\ntitle: Other\n---\n";
+
+            let res = validate(content, FrontMatterFormat::Yaml);
+
+            assert_that(&res.unwrap_err().to_string()).contains("line 4: duplicate key");
+        }
+
+        #[test]
+        fn yaml_bad_indentation() {
+            let indented_key = "---\ntitle: Title\n date: 2020-06-10\n---\n";
+            let nested_map = "---\ntitle: Title\ndate: 2020-06-10\nauthor:\n  name: x\n---\n";
+            let sequence_value = "---\ntitle: Title\ndate: - 2020-06-10\n---\n";
+            let nested_flow = "---\ntitle: Title\ndate: 2020-06-10\ntags: [a, [b]]\n---\n";
+
+            assert_that(
+                &validate(indented_key, FrontMatterFormat::Yaml)
+                    .unwrap_err()
+                    .to_string(),
+            )
+            .contains("line 3");
+            assert_that(
+                &validate(nested_map, FrontMatterFormat::Yaml)
+                    .unwrap_err()
+                    .to_string(),
+            )
+            .contains("line 5");
+            assert_that(&validate(sequence_value, FrontMatterFormat::Yaml)).is_err();
+            assert_that(&validate(nested_flow, FrontMatterFormat::Yaml)).is_err();
+        }
+
+        #[test]
+        fn yaml_single_quotes() {
+            let valid = "---\ntitle: 'It''s'\ndate: 2020-06-10\n---\n";
+            let invalid = "---\ndate: 2020-06-10\ntitle: 'It's'\n---\n";
+
+            assert_that(&validate(valid, FrontMatterFormat::Yaml)).is_ok();
+            assert_that(
+                &validate(invalid, FrontMatterFormat::Yaml)
+                    .unwrap_err()
+                    .to_string(),
+            )
+            .contains("line 3");
+        }
+
+        #[test]
+        fn toml_syntax_error() {
+            let content = "+++\ntitle = \"Title\"\ndate = \"He said \"hi\"\"\n+++\n";
+
+            let res = validate(content, FrontMatterFormat::Toml);
+
+            assert_that(&res.unwrap_err().to_string()).contains("line 3");
+        }
+
+        #[test]
+        fn json_syntax_error() {
+            let content = "{\n  \"title\": \"Title\",\n  \"date\": \"x\" \"y\"\n}\n";
+
+            let res = validate(content, FrontMatterFormat::Json);
+
+            assert_that(&res.unwrap_err().to_string()).contains("line 3");
+        }
+
+        #[test]
+        fn missing_required_field() {
+            let content = "---\ntitle: \"\"\n---\n";
+
+            let res = validate(content, FrontMatterFormat::Yaml);
+
+            assert_that(&res.unwrap_err().to_string()).contains("[title, date]");
+        }
+
+        #[test]
+        fn wrong_format() {
+            let content = "---\ntitle: Title\ndate: 2020-06-10\n---\n";
+
+            let res = validate(content, FrontMatterFormat::Toml);
+
+            assert_that(&res).is_err();
+        }
+
+        #[test]
+        fn without_frontmatter() {
+            assert_that(&validate_note("# Title\n", FrontMatterFormat::Yaml, &[])).is_ok();
+            assert_that(&validate("# Title\n", FrontMatterFormat::Yaml)).is_err();
+        }
+
+        #[test]
+        fn yaml_block_scalars() {
+            let content = "---\ntitle: Title\ndescription: >\n  folded\n  text\nabstract: |\n  line 1\n  line 2\ndate: 2020-06-10\n---\n";
+
+            let res = parse_frontmatter(content).unwrap().unwrap().0;
+
+            assert_that(&res.get_value("description")).is_equal_to(Some("folded text"));
+            assert_that(&res.get_value("abstract")).is_equal_to(Some("line 1\nline 2"));
+            assert_that(&res.get_value("date")).is_equal_to(Some("2020-06-10"));
+        }
+
+        #[test]
+        fn create_note_writes_nothing_if_invalid() {
+            let dir = TempDir::new("new_note-validate");
+            let path = dir.join("note.md");
+            let frontmatter = FrontMatter::new("He said \"hi\"", "2020-06-10");
+            let required = required();
+            let context = TemplateContext::new(&frontmatter, "slug")
+                .with_frontmatter_format(FrontMatterFormat::Yaml)
+                .unwrap()
+                .with_required_fields(&required);
+            let template = NoteTemplate::new("---\ntitle: \"{{title}}\"\ndate: {{date}}\n---\n");
+
+            let res = create_note(&path, &template, &context);
+
+            assert_that(&res).is_err();
+            assert_that(&path.exists()).is_false();
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use super::*;
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs git with `args` in `dir` and returns its trimmed stdout.
+fn git(dir: &Path, args: &[&OsStr]) -> Result<String, NnError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| NnError::GitFailed {
+            arg: format!("'git' failed to start: {}", e),
+        })?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(NnError::GitFailed {
+            arg: format!(
+                "'git {}' {}: {}",
+                args.first()
+                    .map(|a| a.to_string_lossy())
+                    .unwrap_or_default(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        })
+    }
+}
+
+/// Returns the root of the git repository `dir` belongs to.
+pub fn git_toplevel(dir: &Path) -> Result<PathBuf, NnError> {
+    git(dir, &["rev-parse".as_ref(), "--show-toplevel".as_ref()]).map(PathBuf::from)
+}
+
+/// Checks that `name` is a valid name of a branch that does not exist yet in the git
+/// repository of `dir`.
+pub fn git_check_new_branch(dir: &Path, name: &str) -> Result<(), NnError> {
+    git(
+        dir,
+        &[
+            "check-ref-format".as_ref(),
+            "--branch".as_ref(),
+            name.as_ref(),
+        ],
+    )?;
+    let reference = format!("refs/heads/{}", name);
+    let exists = git(
+        dir,
+        &[
+            "rev-parse".as_ref(),
+            "--verify".as_ref(),
+            "--quiet".as_ref(),
+            reference.as_ref(),
+        ],
+    );
+    if exists.is_ok() {
+        return Err(NnError::GitFailed {
+            arg: format!("branch '{}' already exists", name),
+        });
+    }
+
+    Ok(())
+}
+
+/// Creates and checks out the new branch `name` in the git repository of `dir`.
+pub fn git_create_branch(dir: &Path, name: &str) -> Result<(), NnError> {
+    git_check_new_branch(dir, name)?;
+    git(dir, &["checkout".as_ref(), "-b".as_ref(), name.as_ref()]).map(|_| ())
+}
+
+/// Stages `paths` and commits only these with `message` in the git repository of `dir`.
+pub fn git_commit<P: AsRef<Path>>(dir: &Path, paths: &[P], message: &str) -> Result<(), NnError> {
+    // Relative paths are relative to the current directory, but git would resolve them
+    // relative to `dir`.
+    let paths: Vec<PathBuf> = paths
+        .iter()
+        .map(|p| {
+            let p = p.as_ref();
+            p.canonicalize()
+                .or_else(|_| env::current_dir().map(|cwd| cwd.join(p)))
+                .map_err(|e| NnError::GitFailed {
+                    arg: format!("can't resolve '{}': {}", p.display(), e),
+                })
+        })
+        .collect::<Result<_, _>>()?;
+    let mut add: Vec<&OsStr> = vec!["add".as_ref(), "--".as_ref()];
+    add.extend(paths.iter().map(|p| p.as_os_str()));
+    git(dir, &add)?;
+
+    let mut commit: Vec<&OsStr> = vec![
+        "commit".as_ref(),
+        "--message".as_ref(),
+        message.as_ref(),
+        "--".as_ref(),
+    ];
+    commit.extend(paths.iter().map(|p| p.as_os_str()));
+    git(dir, &commit).map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+
+    fn init_repo(name: &str) -> TempDir {
+        let repo = TempDir::new(&format!("git-{}", name));
+        for args in &[
+            vec!["init", "--quiet"],
+            vec!["config", "user.name", "Test"],
+            vec!["config", "user.email", "test@example.com"],
+            vec!["config", "commit.gpgsign", "false"],
+            vec!["commit", "--quiet", "--allow-empty", "--message", "init"],
+        ] {
+            let args: Vec<&OsStr> = args.iter().map(|a| a.as_ref()).collect();
+            git(&repo, &args).unwrap();
+        }
+        repo
+    }
+
+    #[test]
+    fn commit_only_new_note() {
+        let repo = init_repo("commit");
+        let note = repo.join("2020/hello.md");
+        let other = repo.join("other.md");
+        write_content_to_file("Hello", &note).unwrap();
+        write_content_to_file("Other", &other).unwrap();
+        let frontmatter = FrontMatter::new("Hello World", "2020-06-10");
+        let context = TemplateContext::new(&frontmatter, "hello-world");
+        let message = render_template(
+            &NoteTemplate::new(&GitOptions::default().commit_message),
+            &context,
+        )
+        .unwrap();
+
+        let toplevel = git_toplevel(note.parent().unwrap());
+        let res = git_commit(&repo, &[&note], &message);
+
+        assert_that(&toplevel).is_ok();
+        assert_that(&res).is_ok();
+        assert_that(&git(
+            &repo,
+            &["log".as_ref(), "-1".as_ref(), "--format=%s".as_ref()],
+        ))
+        .is_ok()
+        .is_equal_to("note: Hello World".to_owned());
+        assert_that(&git(&repo, &["status".as_ref(), "--porcelain".as_ref()]))
+            .is_ok()
+            .is_equal_to("?? other.md".to_owned());
+    }
+
+    #[test]
+    fn commit_relative_paths() {
+        let repo = init_repo("relative");
+        write_content_to_file("Hello", &repo.join("notes/hello.md")).unwrap();
+        // A relative notes directory like "site/notes" in a repository below the current
+        // directory.
+        let cwd = env::current_dir().unwrap();
+        let up: PathBuf = cwd.components().skip(1).map(|_| "..").collect();
+        let notes = up.join(repo.join("notes").strip_prefix("/").unwrap());
+
+        let res = git_commit(&notes, &[notes.join("hello.md")], "note: Hello");
+
+        assert_that(&notes.is_relative()).is_true();
+        assert_that(&res).is_ok();
+        assert_that(&git(&repo, &["status".as_ref(), "--porcelain".as_ref()]))
+            .is_ok()
+            .is_equal_to(String::new());
+    }
+
+    #[test]
+    fn create_branch() {
+        let repo = init_repo("branch");
+
+        let check = git_check_new_branch(&repo, "draft/hello-world");
+        let res = git_create_branch(&repo, "draft/hello-world");
+        let invalid = git_check_new_branch(&repo, "draft/..hello");
+        let existing = git_check_new_branch(&repo, "draft/hello-world");
+
+        assert_that(&check).is_ok();
+        assert_that(&res).is_ok();
+        assert_that(&invalid).is_err();
+        assert_that(&existing).is_err();
+        assert_that(&git(
+            &repo,
+            &[
+                "rev-parse".as_ref(),
+                "--abbrev-ref".as_ref(),
+                "HEAD".as_ref(),
+            ],
+        ))
+        .is_ok()
+        .is_equal_to("draft/hello-world".to_owned());
+    }
+
+    #[test]
+    fn not_a_repository() {
+        let res = git_toplevel(Path::new("/"));
+
+        assert_that(&res).is_err();
+    }
+}
@@ -0,0 +1,88 @@
+use super::*;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// The note a hook runs for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookNote<'a> {
+    pub path: &'a Path,
+    pub title: &'a str,
+    pub slug: &'a str,
+    pub date: &'a str,
+}
+
+/// Runs `command` with `sh -c` in `dir`. The note is passed in the environment variables
+/// `NEW_NOTE_PATH`, `NEW_NOTE_TITLE`, `NEW_NOTE_SLUG`, and `NEW_NOTE_DATE`.
+pub fn run_hook(command: &str, dir: &Path, note: &HookNote) -> Result<(), NnError> {
+    let path = if note.path.is_absolute() {
+        note.path.to_path_buf()
+    } else {
+        env::current_dir()
+            .map(|cwd| cwd.join(note.path))
+            .unwrap_or_else(|_| note.path.to_path_buf())
+    };
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .env("NEW_NOTE_PATH", &path)
+        .env("NEW_NOTE_TITLE", note.title)
+        .env("NEW_NOTE_SLUG", note.slug)
+        .env("NEW_NOTE_DATE", note.date)
+        .status()
+        .map_err(|e| NnError::HookFailed {
+            arg: format!("'{}' failed to start: {}", command, e),
+        })?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(NnError::HookFailed {
+            arg: format!("'{}' {}", command, status),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+    use std::fs;
+
+    fn hook_note(path: &Path) -> HookNote<'_> {
+        HookNote {
+            path,
+            title: "Hello World",
+            slug: "hello-world",
+            date: "2020-06-10",
+        }
+    }
+
+    #[test]
+    fn hook_environment() {
+        let dir = TempDir::new("new_note-hooks");
+        let path = dir.join("2020-06-10/hello-world.md");
+
+        let res = run_hook(
+            "printf '%s|%s|%s|%s' \"$NEW_NOTE_PATH\" \"$NEW_NOTE_TITLE\" \"$NEW_NOTE_SLUG\" \"$NEW_NOTE_DATE\" > hook.txt",
+            &dir,
+            &hook_note(&path),
+        );
+
+        assert_that(&res).is_ok();
+        assert_that(&fs::read_to_string(dir.join("hook.txt")).unwrap()).is_equal_to(format!(
+            "{}|Hello World|hello-world|2020-06-10",
+            path.display()
+        ));
+    }
+
+    #[test]
+    fn failing_hook() {
+        let res = run_hook("exit 3", &env::temp_dir(), &hook_note(Path::new("note.md")));
+
+        assert_that(&matches!(res, Err(NnError::HookFailed { .. }))).is_true();
+    }
+}
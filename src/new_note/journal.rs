@@ -0,0 +1,207 @@
+use super::*;
+use chrono::prelude::*;
+use std::fs;
+use std::path::Path;
+
+/// Formats `text` as list item `- <time> <text>`; subsequent lines are indented accordingly.
+pub fn journal_entry(
+    date: &DateTime<Local>,
+    text: &str,
+    time_format: &str,
+) -> Result<String, NnError> {
+    let time = format_datetime(date, time_format)?;
+    let mut entry = format!("- {}", time);
+    for (i, line) in text.trim().lines().enumerate() {
+        match (i, line.trim_end()) {
+            (0, line) => entry.push_str(&format!(" {}\n", line)),
+            (_, "") => entry.push('\n'),
+            (_, line) => entry.push_str(&format!("  {}\n", line)),
+        }
+    }
+    if !entry.ends_with('\n') {
+        entry.push('\n');
+    }
+
+    Ok(entry)
+}
+
+/// Appends `entry` to the body of the section starting with the line `heading`, i.e., after its
+/// last entry and before the next heading of any level, so entries never end up in nested
+/// subsections. If there's no such section, it's appended to `content`.
+pub fn append_journal_entry(content: &str, heading: &str, entry: &str) -> String {
+    let heading = heading.trim();
+    let level = |line: &str| {
+        let hashes = line.chars().take_while(|c| *c == '#').count();
+        if hashes > 0 && line[hashes..].starts_with(' ') {
+            Some(hashes)
+        } else {
+            None
+        }
+    };
+    let lines: Vec<_> = content.split_inclusive('\n').collect();
+
+    let start = match lines.iter().position(|l| l.trim() == heading) {
+        Some(start) => start,
+        None => {
+            let mut appended = content.to_string();
+            if !appended.is_empty() && !appended.ends_with('\n') {
+                appended.push('\n');
+            }
+            if !appended.is_empty() && !appended.ends_with("\n\n") {
+                appended.push('\n');
+            }
+            appended.push_str(&format!("{}\n\n{}", heading, entry));
+            return appended;
+        }
+    };
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| level(l).is_some())
+        .map(|i| start + 1 + i)
+        .unwrap_or(lines.len());
+    let last = (start..end)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty())
+        .unwrap_or(start);
+
+    let mut appended: String = lines[..=last].concat();
+    if !appended.ends_with('\n') {
+        appended.push('\n');
+    }
+    if last == start {
+        appended.push('\n');
+    }
+    appended.push_str(entry);
+    if end < lines.len() {
+        appended.push('\n');
+        appended.push_str(&lines[end..].concat());
+    }
+
+    appended
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JournalUpdate {
+    Created,
+    Appended,
+}
+
+/// Appends `entry` under `heading` to the note at `path`; creates the note from `template`
+/// first if it doesn't exist yet.
+pub fn add_journal_entry(
+    path: &Path,
+    template: &NoteTemplate,
+    context: &TemplateContext,
+    heading: &str,
+    entry: &str,
+) -> Result<JournalUpdate, NnError> {
+    if !path.exists() {
+        let content = render_note(template, context)?;
+        let content = append_journal_entry(&content, heading, entry);
+        match write_content_to_file(&content, path) {
+            Ok(_) => return Ok(JournalUpdate::Created),
+            // Somebody else has been faster, so append to their note.
+            Err(NnError::NoteAlreadyExists { .. }) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let content =
+        fs::read_to_string(path).map_err(|e| NnError::FailedToReadNote { arg: e.to_string() })?;
+    let content = append_journal_entry(&content, heading, entry);
+    replace_note_content(&content, path)?;
+
+    Ok(JournalUpdate::Appended)
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+
+    fn date() -> DateTime<Local> {
+        Local.ymd(2020, 06, 10).and_hms(09, 15, 00)
+    }
+
+    #[test]
+    fn entry() {
+        let res = journal_entry(&date(), "First line\n\nSecond line\n", "%H:%M");
+
+        assert_that(&res)
+            .is_ok()
+            .is_equal_to("- 09:15 First line\n\n  Second line\n".to_owned());
+    }
+
+    #[test]
+    fn entry_invalid_format() {
+        let res = journal_entry(&date(), "Text", "%Q");
+
+        assert_that(&res).is_err();
+    }
+
+    #[test]
+    fn append_without_heading() {
+        let res = append_journal_entry("---\ntitle: x\n---\n", "## Journal", "- 09:15 Hi\n");
+
+        assert_that(&res.as_str()).is_equal_to("---\ntitle: x\n---\n\n## Journal\n\n- 09:15 Hi\n");
+    }
+
+    #[test]
+    fn append_to_empty_section() {
+        let res = append_journal_entry("## Journal\n", "## Journal", "- 09:15 Hi\n");
+
+        assert_that(&res.as_str()).is_equal_to("## Journal\n\n- 09:15 Hi\n");
+    }
+
+    #[test]
+    fn append_to_section() {
+        let content = "# Day\n\n## Journal\n\n- 08:00 Up\n\n### Details\n\nx\n\n## Todo\n\n- y\n";
+
+        let res = append_journal_entry(content, "## Journal", "- 09:15 Hi\n");
+
+        assert_that(&res.as_str()).is_equal_to(
+            "# Day\n\n## Journal\n\n- 08:00 Up\n- 09:15 Hi\n\n### Details\n\nx\n\n## Todo\n\n- y\n",
+        );
+    }
+
+    #[test]
+    fn append_to_section_with_only_subsections() {
+        let content = "## Journal\n\n### Details\n\nx\n";
+
+        let res = append_journal_entry(content, "## Journal", "- 09:15 Hi\n");
+
+        assert_that(&res.as_str()).is_equal_to("## Journal\n\n- 09:15 Hi\n\n### Details\n\nx\n");
+    }
+
+    #[test]
+    fn append_to_last_section() {
+        let content = "## Journal\n\n- 08:00 Up\n\n\n";
+
+        let res = append_journal_entry(content, "## Journal", "- 09:15 Hi\n");
+
+        assert_that(&res.as_str()).is_equal_to("## Journal\n\n- 08:00 Up\n- 09:15 Hi\n");
+    }
+
+    #[test]
+    fn add_entries() {
+        let dir = TempDir::new("new_note-journal");
+        let path = dir.join("journal.md");
+        let template = NoteTemplate::new("# {{title}}\n");
+        let frontmatter = FrontMatter::new("Journal", "2020-06-10");
+        let context = TemplateContext::new(&frontmatter, "journal");
+
+        let first = add_journal_entry(&path, &template, &context, "## Log", "- 1\n");
+        let second = add_journal_entry(&path, &template, &context, "## Log", "- 2\n");
+
+        assert_that(&first)
+            .is_ok()
+            .is_equal_to(JournalUpdate::Created);
+        assert_that(&second)
+            .is_ok()
+            .is_equal_to(JournalUpdate::Appended);
+        assert_that(&fs::read_to_string(&path).unwrap())
+            .is_equal_to("# Journal\n\n## Log\n\n- 1\n- 2\n".to_owned());
+        assert_that(&fs::read_dir(&dir).unwrap().count()).is_equal_to(1);
+    }
+}
@@ -0,0 +1,82 @@
+use failure::Fail;
+
+mod assets;
+mod config;
+mod date;
+mod editor;
+mod file;
+mod frontmatter;
+mod git;
+mod hooks;
+mod journal;
+mod note;
+mod path;
+mod prompt;
+mod slug;
+mod template;
+mod update;
+
+pub use self::assets::*;
+pub use self::config::*;
+pub use self::date::*;
+pub use self::editor::*;
+pub use self::file::*;
+pub use self::frontmatter::*;
+pub use self::git::*;
+pub use self::hooks::*;
+pub use self::journal::*;
+pub use self::note::*;
+pub use self::path::*;
+pub use self::prompt::*;
+pub use self::slug::*;
+pub use self::template::*;
+pub use self::update::*;
+
+#[derive(Debug, Fail)]
+pub enum NnError {
+    #[fail(display = "Could to parse publication date because {}", arg)]
+    FailedToParsePublicationDate { arg: String },
+    #[fail(display = "Could to create new note because {}", arg)]
+    FailedToCreateNewNote { arg: String },
+    #[fail(display = "Could not exec editor because {}", arg)]
+    FailedToExecEditor { arg: String },
+    #[fail(display = "Editor failed because {}", arg)]
+    EditorFailed { arg: String },
+    #[fail(display = "Could not render frontmatter template because {}", arg)]
+    FailedToRenderFrontmatterTemplate { arg: String },
+    #[fail(display = "Could not write note file because {}", arg)]
+    FailedToWriteNoteFile { arg: String },
+    #[fail(display = "Invalid frontmatter field '{}'", arg)]
+    InvalidFrontMatterField { arg: String },
+    #[fail(
+        display = "Frontmatter field '{}' would be shadowed by the template variable of the same name",
+        arg
+    )]
+    ReservedFrontMatterField { arg: String },
+    #[fail(display = "Invalid note kind because {}", arg)]
+    InvalidNoteKind { arg: String },
+    #[fail(display = "Could not load template because {}", arg)]
+    FailedToLoadTemplate { arg: String },
+    #[fail(display = "Invalid path pattern because {}", arg)]
+    InvalidPathPattern { arg: String },
+    #[fail(display = "Note '{}' already exists", arg)]
+    NoteAlreadyExists { arg: String },
+    #[fail(display = "Could not read note because {}", arg)]
+    FailedToReadNote { arg: String },
+    #[fail(display = "Invalid frontmatter because {}", arg)]
+    InvalidFrontMatter { arg: String },
+    #[fail(display = "Invalid date format '{}'", arg)]
+    InvalidDateFormat { arg: String },
+    #[fail(display = "Invalid timezone because {}", arg)]
+    InvalidTimezone { arg: String },
+    #[fail(display = "Invalid asset because {}", arg)]
+    InvalidAsset { arg: String },
+    #[fail(display = "Could not copy asset because {}", arg)]
+    FailedToCopyAsset { arg: String },
+    #[fail(display = "Git failed because {}", arg)]
+    GitFailed { arg: String },
+    #[fail(display = "Hook failed because {}", arg)]
+    HookFailed { arg: String },
+    #[fail(display = "Could not read answer because {}", arg)]
+    FailedToReadAnswer { arg: String },
+}
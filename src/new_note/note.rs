@@ -0,0 +1,282 @@
+use super::*;
+use chrono::prelude::*;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An existing note.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Note {
+    pub path: PathBuf,
+    pub frontmatter: FrontMatter,
+    #[serde(skip)]
+    pub body: String,
+}
+
+impl Note {
+    pub fn title(&self) -> &str {
+        self.frontmatter.get_value("title").unwrap_or("")
+    }
+
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.frontmatter
+            .get_value("date")
+            .and_then(parse_frontmatter_date)
+            .map(|dt| dt.date())
+    }
+
+    /// The date in the local timezone; dates without a time are at midnight.
+    pub fn datetime(&self) -> Option<DateTime<Local>> {
+        self.frontmatter
+            .get_value("date")
+            .and_then(parse_frontmatter_date)
+            .and_then(naive_to_local)
+    }
+
+    pub fn is_draft(&self) -> bool {
+        self.frontmatter.is_draft()
+    }
+}
+
+/// Reads the note at `path`; returns `None` if the file does not have a frontmatter.
+pub fn read_note(path: &Path) -> Result<Option<Note>, NnError> {
+    let content = fs::read_to_string(path).map_err(|e| NnError::FailedToReadNote {
+        arg: format!("failed to read '{}' because {}", path.display(), e),
+    })?;
+    let note = parse_frontmatter(&content)
+        .map_err(|e| NnError::FailedToReadNote {
+            arg: format!("'{}' has an invalid frontmatter: {}", path.display(), e),
+        })?
+        .map(|(frontmatter, body)| Note {
+            path: path.to_path_buf(),
+            frontmatter,
+            body: body.to_string(),
+        });
+
+    Ok(note)
+}
+
+/// Finds all markdown files in `directories`, skipping hidden files and directories.
+pub fn find_notes<P: AsRef<Path>>(directories: &[P]) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = directories
+        .iter()
+        .flat_map(|d| {
+            walkdir::WalkDir::new(d)
+                .into_iter()
+                .filter_entry(|e| {
+                    e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.')
+                })
+                .filter_map(|e| e.ok())
+        })
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| p.extension().is_some_and(|e| e == "md"))
+        .collect();
+    paths.sort();
+    paths.dedup();
+
+    paths
+}
+
+/// Criteria for listing and searching notes; unset criteria match all notes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NoteFilter {
+    /// Notes must have all of these tags
+    pub tags: Vec<String>,
+    /// Notes must have all of these categories
+    pub categories: Vec<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub draft: Option<bool>,
+    /// Case-insensitive text to find in title or body
+    pub text: Option<String>,
+}
+
+impl NoteFilter {
+    pub fn matches(&self, note: &Note) -> bool {
+        let has_all = |key: &str, values: &[String]| {
+            let list = note.frontmatter.get_list(key);
+            values.iter().all(|v| list.contains(&v.as_str()))
+        };
+        let date = note.date();
+
+        has_all("tags", &self.tags)
+            && has_all("categories", &self.categories)
+            && self
+                .since
+                .is_none_or(|since| date.is_some_and(|d| d >= since))
+            && self
+                .until
+                .is_none_or(|until| date.is_some_and(|d| d <= until))
+            && self.draft.is_none_or(|draft| note.is_draft() == draft)
+            && self.text.as_ref().is_none_or(|text| {
+                let text = text.to_lowercase();
+                note.title().to_lowercase().contains(&text)
+                    || note.body.to_lowercase().contains(&text)
+            })
+    }
+}
+
+/// Collects the sorted, distinct tags of all notes in `directories`.
+pub fn known_tags<P: AsRef<Path>>(directories: &[P]) -> Vec<String> {
+    let mut tags: Vec<String> = find_notes(directories)
+        .iter()
+        .filter_map(|path| read_note(path).ok().and_then(|note| note))
+        .flat_map(|note| {
+            note.frontmatter
+                .get_list("tags")
+                .into_iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    tags.sort();
+    tags.dedup();
+
+    tags
+}
+
+/// Completes `tag` to the only known tag starting with it; otherwise `tag` is kept.
+pub fn complete_tag(tag: &str, known_tags: &[String]) -> String {
+    if known_tags.iter().any(|t| t == tag) {
+        return tag.to_string();
+    }
+    let mut candidates = known_tags.iter().filter(|t| t.starts_with(tag));
+    match (candidates.next(), candidates.next()) {
+        (Some(candidate), None) => candidate.clone(),
+        _ => tag.to_string(),
+    }
+}
+
+/// Formats `notes` as table with date, title, tags, and path.
+pub fn notes_to_table(notes: &[Note]) -> String {
+    let mut rows = vec![vec![
+        "DATE".to_string(),
+        "TITLE".to_string(),
+        "TAGS".to_string(),
+        "PATH".to_string(),
+    ]];
+    for note in notes {
+        rows.push(vec![
+            note.date().map(|d| d.to_string()).unwrap_or_default(),
+            note.title().to_string(),
+            note.frontmatter.get_list("tags").join(", "),
+            note.path.display().to_string(),
+        ]);
+    }
+
+    let mut widths = vec![0; 4];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+
+    const NOTE: &str = "---\ntitle: Note\ndate: 2020-06-10\n---\n\nHello World!\n";
+
+    fn note(title: &str, date: &str, tags: &[&str], draft: bool, body: &str) -> Note {
+        let mut frontmatter = FrontMatter::new(title, date);
+        for tag in tags {
+            frontmatter.add_to_list("tags", tag);
+        }
+        if draft {
+            frontmatter.set_value("draft", "true");
+        }
+        Note {
+            path: PathBuf::from(format!("{}.md", title)),
+            frontmatter,
+            body: body.to_owned(),
+        }
+    }
+
+    #[test]
+    fn filter_tags_and_drafts() {
+        let rust = note("Rust", "2020-06-10", &["rust", "cli"], false, "");
+        let draft = note("Draft", "2020-06-10", &["rust"], true, "");
+        let filter = NoteFilter {
+            tags: vec!["rust".to_owned()],
+            draft: Some(false),
+            ..NoteFilter::default()
+        };
+
+        assert_that(&filter.matches(&rust)).is_true();
+        assert_that(&filter.matches(&draft)).is_false();
+    }
+
+    #[test]
+    fn filter_date_range() {
+        let filter = NoteFilter {
+            since: Some(NaiveDate::from_ymd(2020, 06, 01)),
+            until: Some(NaiveDate::from_ymd(2020, 06, 30)),
+            ..NoteFilter::default()
+        };
+
+        assert_that(&filter.matches(&note("a", "2020-06-10 12:30", &[], false, ""))).is_true();
+        assert_that(&filter.matches(&note("b", "2020-07-01", &[], false, ""))).is_false();
+        assert_that(&filter.matches(&note("c", "", &[], false, ""))).is_false();
+    }
+
+    #[test]
+    fn filter_text() {
+        let filter = NoteFilter {
+            text: Some("WORLD".to_owned()),
+            ..NoteFilter::default()
+        };
+
+        assert_that(&filter.matches(&note("Hello", "", &[], false, "Hello World"))).is_true();
+        assert_that(&filter.matches(&note("World", "", &[], false, ""))).is_true();
+        assert_that(&filter.matches(&note("Hello", "", &[], false, "Hello"))).is_false();
+    }
+
+    #[test]
+    fn table() {
+        let notes = vec![
+            note("Rust", "2020-06-10", &["rust", "cli"], false, ""),
+            note("Über", "", &[], false, ""),
+        ];
+        let expected = "DATE        TITLE  TAGS       PATH\n\
+                        2020-06-10  Rust   rust, cli  Rust.md\n\
+                        \x20           Über              Über.md\n";
+
+        let res = notes_to_table(&notes);
+
+        assert_that(&res.as_str()).is_equal_to(expected);
+    }
+
+    #[test]
+    fn find_and_read_notes() {
+        let dir = TempDir::new("new_note-find");
+        write_content_to_file(NOTE, &dir.join("2020/note.md")).unwrap();
+        write_content_to_file("no frontmatter", &dir.join("plain.md")).unwrap();
+        write_content_to_file(NOTE, &dir.join(".hidden/note.md")).unwrap();
+        write_content_to_file(NOTE, &dir.join("image.png")).unwrap();
+
+        let paths = find_notes(&[&dir]);
+        let notes: Vec<_> = paths.iter().map(|p| read_note(p).unwrap()).collect();
+
+        assert_that(&paths).is_equal_to(vec![dir.join("2020/note.md"), dir.join("plain.md")]);
+        assert_that(&notes[0]).is_some();
+        assert_that(&notes[1]).is_none();
+    }
+}
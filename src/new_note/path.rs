@@ -0,0 +1,100 @@
+use super::*;
+use chrono::prelude::*;
+use std::path::PathBuf;
+
+/// Expands `pattern` into the path of a note relative to the notes directory. Patterns may use
+/// the variables `{year}`, `{month}`, `{day}`, `{date}` (`YYYY-MM-DD`), `{slug}`, and `{kind}`,
+/// e.g., `{year}/{month}/{slug}/index.md` or `{date}-{slug}.md`.
+pub fn note_path(
+    pattern: &str,
+    date: &DateTime<Local>,
+    slug: &str,
+    kind: &str,
+) -> Result<PathBuf, NnError> {
+    let invalid = |reason: &str| NnError::InvalidPathPattern {
+        arg: format!("'{}' {}", pattern, reason),
+    };
+
+    let mut path = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        path.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| invalid("contains an unclosed '{'"))?;
+        let value = match &rest[start + 1..start + end] {
+            "year" => date.format("%Y").to_string(),
+            "month" => date.format("%m").to_string(),
+            "day" => date.format("%d").to_string(),
+            "date" => date_to_iso_day(date),
+            "slug" => slug.to_string(),
+            "kind" => kind.to_string(),
+            var => return Err(invalid(&format!("contains unknown variable '{{{}}}'", var))),
+        };
+        path.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    path.push_str(rest);
+
+    if path.is_empty() || path.ends_with('/') {
+        return Err(invalid("does not end in a file name"));
+    }
+    let path = PathBuf::from(path);
+    if !path
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+    {
+        return Err(invalid("must be a relative path without '.' or '..'"));
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use spectral::prelude::*;
+
+    fn path(pattern: &str) -> Result<PathBuf, NnError> {
+        let date = Local.ymd(2020, 06, 10).and_hms(12, 30, 00);
+        note_path(pattern, &date, "hello-world", "post")
+    }
+
+    #[test]
+    fn default() {
+        assert_that(&path(DEFAULT_PATH_PATTERN))
+            .is_ok()
+            .is_equal_to(PathBuf::from("2020-06-10/hello-world.md"));
+    }
+
+    #[test]
+    fn page_bundle() {
+        assert_that(&path("{year}/{month}/{day}/{slug}/index.md"))
+            .is_ok()
+            .is_equal_to(PathBuf::from("2020/06/10/hello-world/index.md"));
+    }
+
+    #[test]
+    fn jekyll() {
+        assert_that(&path("_{kind}s/{date}-{slug}.md"))
+            .is_ok()
+            .is_equal_to(PathBuf::from("_posts/2020-06-10-hello-world.md"));
+    }
+
+    #[test]
+    fn unknown_variable() {
+        assert_that(&path("{title}.md")).is_err();
+    }
+
+    #[test]
+    fn unclosed_variable() {
+        assert_that(&path("{slug.md")).is_err();
+    }
+
+    #[test]
+    fn escaping_notes_directory() {
+        assert_that(&path("../{slug}.md")).is_err();
+        assert_that(&path("/tmp/{slug}.md")).is_err();
+        assert_that(&path("{slug}/")).is_err();
+    }
+}
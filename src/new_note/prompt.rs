@@ -0,0 +1,179 @@
+use super::*;
+use std::io::prelude::*;
+
+/// Whether stdin is a terminal and a user may answer prompts.
+pub fn is_interactive() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+}
+
+/// Asks the user for the properties of a new note. Reader and writer are usually stdin and
+/// stdout.
+pub struct Prompt<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    pub fn new(reader: R, writer: W) -> Prompt<R, W> {
+        Prompt { reader, writer }
+    }
+
+    pub fn ask_for_title(&mut self) -> Result<String, NnError> {
+        loop {
+            self.write("Title: ")?;
+            match self.read_line()? {
+                Some(ref title) if title.is_empty() => self.write("A title is required.\n")?,
+                Some(title) => return Ok(title),
+                None => {
+                    return Err(NnError::FailedToReadAnswer {
+                        arg: "no title has been entered".to_string(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Asks for comma separated tags and completes unique prefixes of `known_tags`; `?` lists
+    /// all known tags.
+    pub fn ask_for_tags(&mut self, known_tags: &[String]) -> Result<Vec<String>, NnError> {
+        loop {
+            self.write("Tags, comma separated ('?' lists known tags): ")?;
+            let answer = match self.read_line()? {
+                Some(ref answer) if answer == "?" => {
+                    self.write(&format!("Known tags: {}\n", known_tags.join(", ")))?;
+                    continue;
+                }
+                Some(answer) => answer,
+                None => return Ok(Vec::new()),
+            };
+
+            let mut tags: Vec<String> = Vec::new();
+            for tag in answer.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+                let tag = complete_tag(tag, known_tags);
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            return Ok(tags);
+        }
+    }
+
+    pub fn ask_for_description(&mut self) -> Result<Option<String>, NnError> {
+        self.write("Description: ")?;
+        let description = self.read_line()?.filter(|d| !d.is_empty());
+
+        Ok(description)
+    }
+
+    /// Asks for a publication date as understood by `parse_date`; `None` means now.
+    pub fn ask_for_date(&mut self) -> Result<Option<String>, NnError> {
+        loop {
+            self.write("Date [now]: ")?;
+            match self.read_line()? {
+                Some(ref date) if date.is_empty() => return Ok(None),
+                Some(date) => match str_date_to_date(&date) {
+                    Ok(_) => return Ok(Some(date)),
+                    Err(e) => self.write(&format!("{}\n", e))?,
+                },
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn write(&mut self, text: &str) -> Result<(), NnError> {
+        self.writer
+            .write_all(text.as_bytes())
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| NnError::FailedToReadAnswer { arg: e.to_string() })
+    }
+
+    /// Returns `None` at the end of input.
+    fn read_line(&mut self) -> Result<Option<String>, NnError> {
+        let mut line = String::new();
+        let len = self
+            .reader
+            .read_line(&mut line)
+            .map_err(|e| NnError::FailedToReadAnswer { arg: e.to_string() })?;
+        if len == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(line.trim().to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+
+    fn tags() -> Vec<String> {
+        vec!["rust".to_owned(), "ruby".to_owned(), "shell".to_owned()]
+    }
+
+    #[test]
+    fn complete_tags() {
+        assert_that(&complete_tag("sh", &tags())).is_equal_to("shell".to_owned());
+        assert_that(&complete_tag("ru", &tags())).is_equal_to("ru".to_owned());
+        assert_that(&complete_tag("go", &tags())).is_equal_to("go".to_owned());
+    }
+
+    #[test]
+    fn ask_for_note() {
+        let input = "\nHello World\n?\nrus, sh, go, shell\n\nnext day\n+1d\n";
+        let mut output = Vec::new();
+        let mut prompt = Prompt::new(input.as_bytes(), &mut output);
+
+        let title = prompt.ask_for_title();
+        let tags = prompt.ask_for_tags(&tags());
+        let description = prompt.ask_for_description();
+        let date = prompt.ask_for_date();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_that(&title)
+            .is_ok()
+            .is_equal_to("Hello World".to_owned());
+        assert_that(&tags).is_ok().is_equal_to(vec![
+            "rust".to_owned(),
+            "shell".to_owned(),
+            "go".to_owned(),
+        ]);
+        assert_that(&description).is_ok().is_none();
+        assert_that(&date)
+            .is_ok()
+            .is_equal_to(Some("+1d".to_owned()));
+        assert_that(&output.as_str()).contains("A title is required.");
+        assert_that(&output.as_str()).contains("Known tags: rust, ruby, shell");
+        assert_that(&output.as_str()).contains("'next day' is none of");
+    }
+
+    #[test]
+    fn ask_for_title_at_end_of_input() {
+        let mut output = Vec::new();
+        let mut prompt = Prompt::new("".as_bytes(), &mut output);
+
+        let res = prompt.ask_for_title();
+
+        assert_that(&res).is_err();
+    }
+
+    #[test]
+    fn known_tags_of_notes() {
+        let notes = TempDir::new("new_note-known-tags");
+        write_content_to_file(
+            "---\ntitle: A\ntags: [shell, rust]\n---\n",
+            &notes.join("a.md"),
+        )
+        .unwrap();
+        write_content_to_file(
+            "---\ntitle: B\ntags: [rust]\n---\n",
+            &notes.join("b/index.md"),
+        )
+        .unwrap();
+
+        let res = known_tags(&[&notes]);
+
+        assert_that(&res).is_equal_to(vec!["rust".to_owned(), "shell".to_owned()]);
+    }
+}
@@ -0,0 +1,191 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlugOptions {
+    /// ISO 639-1 code of the language used to transliterate non-ASCII characters
+    pub language: String,
+    /// Longer slugs are cut at the last word boundary before
+    pub max_length: usize,
+}
+
+impl Default for SlugOptions {
+    fn default() -> Self {
+        SlugOptions {
+            language: "de".to_string(),
+            max_length: 80,
+        }
+    }
+}
+
+pub fn title_to_file_name(title: &str, options: &SlugOptions) -> String {
+    let mut res = slugify(title, options);
+    res.push_str(".md");
+    res
+}
+
+/// Creates a lower case ASCII slug with words separated by single dashes.
+pub fn slugify(title: &str, options: &SlugOptions) -> String {
+    let mut slug = String::new();
+
+    for c in title.chars() {
+        for c in transliterate(c, &options.language).chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if c == '\'' {
+                // Keep contractions like "don't" together.
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+
+    if slug.len() <= options.max_length {
+        return slug.to_string();
+    }
+    // Slug is pure ASCII, so slicing by bytes is safe.
+    match slug[..=options.max_length].rfind('-') {
+        Some(idx) if idx > 0 => slug[..idx].to_string(),
+        _ => slug[..options.max_length].to_string(),
+    }
+}
+
+fn transliterate(c: char, language: &str) -> String {
+    let special = match (language, c) {
+        ("de", 'ä') | ("de", 'Ä') => Some("ae"),
+        ("de", 'ö') | ("de", 'Ö') => Some("oe"),
+        ("de", 'ü') | ("de", 'Ü') => Some("ue"),
+        ("da", 'å') | ("da", 'Å') | ("no", 'å') | ("no", 'Å') => Some("aa"),
+        ("da", 'ø') | ("da", 'Ø') | ("no", 'ø') | ("no", 'Ø') => Some("oe"),
+        _ => None,
+    };
+
+    match special {
+        Some(s) => s.to_string(),
+        None if c.is_ascii() => c.to_string(),
+        None => deunicode::deunicode_char(c).unwrap_or("-").to_string(),
+    }
+}
+
+/// Maximum number tried by `--suffix` before giving up
+pub const MAX_SLUG_SUFFIX: usize = 100;
+
+/// Returns the `n`-th alternative for `slug` in case of a collision, starting with `slug-2`.
+pub fn suffixed_slug(slug: &str, n: usize) -> String {
+    if n < 2 {
+        slug.to_string()
+    } else {
+        format!("{}-{}", slug, n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use spectral::prelude::*;
+
+    #[test]
+    fn title_to_file_name_okay() {
+        let title = "This is just a 'Punk, Rock' song";
+        let expected = "this-is-just-a-punk-rock-song.md".to_owned();
+
+        let res = title_to_file_name(title, &SlugOptions::default());
+
+        assert_that(&res).is_equal_to(expected);
+    }
+
+    mod slugify {
+        use super::*;
+
+        fn slug(title: &str) -> String {
+            slugify(title, &SlugOptions::default())
+        }
+
+        #[test]
+        fn punctuation() {
+            assert_that(&slug("What? Rust & Go: A/B testing!"))
+                .is_equal_to("what-rust-go-a-b-testing".to_owned());
+        }
+
+        #[test]
+        fn contractions() {
+            assert_that(&slug("Don't panic, it's fine"))
+                .is_equal_to("dont-panic-its-fine".to_owned());
+        }
+
+        #[test]
+        fn collapses_and_trims_dashes() {
+            assert_that(&slug("  -- Hello --  World -- ")).is_equal_to("hello-world".to_owned());
+        }
+
+        #[test]
+        fn transliterates_german() {
+            assert_that(&slug("Über Größe und Café"))
+                .is_equal_to("ueber-groesse-und-cafe".to_owned());
+        }
+
+        #[test]
+        fn transliterates_other_language() {
+            let options = SlugOptions {
+                language: "en".to_owned(),
+                ..Default::default()
+            };
+
+            let res = slugify("Über Größe und Café", &options);
+
+            assert_that(&res).is_equal_to("uber-grosse-und-cafe".to_owned());
+        }
+
+        #[test]
+        fn transliterates_danish() {
+            let options = SlugOptions {
+                language: "da".to_owned(),
+                ..Default::default()
+            };
+
+            let res = slugify("Smørrebrød på Ærø", &options);
+
+            assert_that(&res).is_equal_to("smoerrebroed-paa-aeroe".to_owned());
+        }
+
+        #[test]
+        fn max_length_cuts_at_word_boundary() {
+            let options = SlugOptions {
+                max_length: 12,
+                ..Default::default()
+            };
+
+            let res = slugify("Hello wonderful world", &options);
+
+            assert_that(&res).is_equal_to("hello".to_owned());
+        }
+
+        #[test]
+        fn max_length_keeps_word_ending_at_boundary() {
+            let options = SlugOptions {
+                max_length: 15,
+                ..Default::default()
+            };
+
+            let res = slugify("Hello wonderful world", &options);
+
+            assert_that(&res).is_equal_to("hello-wonderful".to_owned());
+        }
+
+        #[test]
+        fn max_length_cuts_long_word() {
+            let options = SlugOptions {
+                max_length: 5,
+                ..Default::default()
+            };
+
+            let res = slugify("Supercalifragilistic", &options);
+
+            assert_that(&res).is_equal_to("super".to_owned());
+        }
+    }
+
+    #[test]
+    fn suffixed_slug_okay() {
+        assert_that(&suffixed_slug("hello", 1)).is_equal_to("hello".to_owned());
+        assert_that(&suffixed_slug("hello", 2)).is_equal_to("hello-2".to_owned());
+    }
+}
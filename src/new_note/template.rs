@@ -0,0 +1,719 @@
+use super::*;
+use chrono::prelude::*;
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, JsonRender, JsonValue, Output,
+    RenderContext, RenderError,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::Path;
+
+/// Names of the template variables besides the frontmatter fields, which take precedence over
+/// fields of the same name.
+pub const TEMPLATE_VARIABLES: &[&str] = &[
+    "frontmatter",
+    "frontmatter_block",
+    "slug",
+    "assets",
+    "published",
+];
+
+/// Values available to templates. Frontmatter fields may be referenced as `{{title}}` as well
+/// as `{{frontmatter.title}}`; `{{frontmatter_block}}` is the complete, serialized
+/// frontmatter. Fields named like one of the `TEMPLATE_VARIABLES` are only available via
+/// `{{frontmatter.<name>}}`.
+#[derive(Debug, Serialize)]
+pub struct TemplateContext<'a> {
+    #[serde(flatten)]
+    fields: &'a FrontMatter,
+    pub frontmatter: &'a FrontMatter,
+    pub frontmatter_block: String,
+    pub slug: &'a str,
+    /// Files of a page bundle with `{{name}}` and `{{is_image}}`
+    pub assets: &'a [Asset],
+    /// Publication date as `{{published.day}}`, `{{published.time}}`, `{{published.rfc3339}}`,
+    /// `{{published.offset}}`, and `{{published.timestamp}}`
+    pub published: Option<PublicationDate>,
+    /// Body appended to the rendered template
+    #[serde(skip)]
+    pub body: Option<&'a str>,
+    /// Format the frontmatter of rendered notes is validated against; detected if not set
+    #[serde(skip)]
+    pub frontmatter_format: Option<FrontMatterFormat>,
+    /// Fields rendered notes must contain
+    #[serde(skip)]
+    pub required_fields: &'a [String],
+    /// Options of the helper `slugify`, which should match the slugs of file names
+    #[serde(skip)]
+    pub slug_options: SlugOptions,
+}
+
+impl<'a> TemplateContext<'a> {
+    pub fn new(frontmatter: &'a FrontMatter, slug: &'a str) -> TemplateContext<'a> {
+        TemplateContext {
+            fields: frontmatter,
+            frontmatter,
+            frontmatter_block: yaml_frontmatter_block(&OrderedFields(frontmatter)),
+            slug,
+            assets: &[],
+            published: None,
+            body: None,
+            frontmatter_format: None,
+            required_fields: &[],
+            slug_options: SlugOptions::default(),
+        }
+    }
+
+    pub fn with_slug_options(self, slug_options: &SlugOptions) -> TemplateContext<'a> {
+        TemplateContext {
+            slug_options: slug_options.clone(),
+            ..self
+        }
+    }
+
+    pub fn with_assets(self, assets: &'a [Asset]) -> TemplateContext<'a> {
+        TemplateContext { assets, ..self }
+    }
+
+    pub fn with_publication_date(self, date: &DateTime<Local>) -> TemplateContext<'a> {
+        TemplateContext {
+            published: Some(PublicationDate::new(date)),
+            ..self
+        }
+    }
+
+    pub fn with_required_fields(self, required_fields: &'a [String]) -> TemplateContext<'a> {
+        TemplateContext {
+            required_fields,
+            ..self
+        }
+    }
+
+    pub fn with_frontmatter_format(
+        self,
+        format: FrontMatterFormat,
+    ) -> Result<TemplateContext<'a>, NnError> {
+        let frontmatter_block = frontmatter_block(self.frontmatter, format)?;
+
+        Ok(TemplateContext {
+            frontmatter_block,
+            frontmatter_format: Some(format),
+            ..self
+        })
+    }
+
+    pub fn with_body(self, body: &'a str) -> TemplateContext<'a> {
+        TemplateContext {
+            body: Some(body),
+            ..self
+        }
+    }
+}
+
+const TEMPLATE_EXTENSION: &str = "hbs";
+const DEFAULT_TEMPLATE_NAME: &str = "note";
+
+/// A note template together with the partials it may use as `{{> name}}`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NoteTemplate {
+    pub template: String,
+    pub partials: BTreeMap<String, String>,
+}
+
+impl NoteTemplate {
+    pub fn new(template: &str) -> NoteTemplate {
+        NoteTemplate {
+            template: template.to_string(),
+            partials: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the template for `kind` from `source`. A file source may point to a template file
+    /// or to a directory of `*.hbs` files. In a directory, `<kind>.hbs`, or if missing
+    /// `note.hbs`, is the template while all other files are partials named by their file stem.
+    pub fn load(source: &TemplateSource, kind: &str) -> Result<NoteTemplate, NnError> {
+        let path = match source {
+            TemplateSource::Inline(template) => return Ok(NoteTemplate::new(template)),
+            TemplateSource::File(path) => path,
+        };
+
+        if path.is_dir() {
+            NoteTemplate::from_dir(path, kind)
+        } else {
+            let template = fs::read_to_string(path).map_err(|e| NnError::FailedToLoadTemplate {
+                arg: format!("failed to read '{}' because {}", path.display(), e),
+            })?;
+            Ok(NoteTemplate::new(&template))
+        }
+    }
+
+    fn from_dir(dir: &Path, kind: &str) -> Result<NoteTemplate, NnError> {
+        let read_err = |e: std::io::Error| NnError::FailedToLoadTemplate {
+            arg: format!("failed to read '{}' because {}", dir.display(), e),
+        };
+
+        let mut templates = BTreeMap::new();
+        for entry in fs::read_dir(dir).map_err(read_err)? {
+            let path = entry.map_err(read_err)?.path();
+            if !path.is_file() || path.extension().is_none_or(|e| e != TEMPLATE_EXTENSION) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                let template = fs::read_to_string(&path).map_err(read_err)?;
+                templates.insert(name.to_string(), template);
+            }
+        }
+
+        let template = templates
+            .remove(kind)
+            .or_else(|| templates.remove(DEFAULT_TEMPLATE_NAME))
+            .ok_or_else(|| NnError::FailedToLoadTemplate {
+                arg: format!(
+                    "'{}' contains neither {}.{} nor {}.{}",
+                    dir.display(),
+                    kind,
+                    TEMPLATE_EXTENSION,
+                    DEFAULT_TEMPLATE_NAME,
+                    TEMPLATE_EXTENSION
+                ),
+            })?;
+
+        Ok(NoteTemplate {
+            template,
+            partials: templates,
+        })
+    }
+}
+
+/// Creates the note and returns its content.
+pub fn create_note(
+    path: &Path,
+    template: &NoteTemplate,
+    context: &TemplateContext,
+) -> Result<String, NnError> {
+    let content = render_note(template, context)?;
+    let _ = write_content_to_file(&content, &path)?;
+
+    Ok(content)
+}
+
+/// Renders `template`, appends the body, and validates the frontmatter of the result.
+pub fn render_note(template: &NoteTemplate, context: &TemplateContext) -> Result<String, NnError> {
+    let mut content = render_template(template, context)?;
+    if let Some(body) = context.body {
+        content = append_body(&content, body);
+    }
+
+    let format = context
+        .frontmatter_format
+        .or_else(|| FrontMatterFormat::detect(&content))
+        .unwrap_or_default();
+    validate_note(&content, format, context.required_fields)?;
+
+    Ok(content)
+}
+
+/// Appends `body` to `content` separated by exactly one empty line.
+pub fn append_body(content: &str, body: &str) -> String {
+    let body = body.trim_start_matches(['\r', '\n']);
+    if body.is_empty() {
+        return content.to_string();
+    }
+
+    let mut note = content.trim_end().to_string();
+    note.push_str("\n\n");
+    note.push_str(body);
+    if !note.ends_with('\n') {
+        note.push('\n');
+    }
+
+    note
+}
+
+/// Splits an existing markdown file into its frontmatter, if any, and its body.
+pub fn split_note(content: &str) -> Result<(FrontMatter, &str), NnError> {
+    let split = parse_frontmatter(content)?.unwrap_or_else(|| (FrontMatter::default(), content));
+
+    Ok(split)
+}
+
+/// Renders `template` with the helpers `slugify`, `date`, `upper`, `lower`, `default`, and
+/// `join`. Since notes are markdown, values are not HTML escaped.
+pub fn render_template(
+    template: &NoteTemplate,
+    context: &TemplateContext,
+) -> Result<String, NnError> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    register_helpers(&mut handlebars, &context.slug_options);
+    for (name, partial) in &template.partials {
+        handlebars
+            .register_partial(name, partial)
+            .map_err(|e| NnError::FailedToRenderFrontmatterTemplate { arg: e.to_string() })?;
+    }
+    handlebars
+        .register_template_string("frontmatter", &template.template)
+        .map_err(|e| NnError::FailedToRenderFrontmatterTemplate { arg: e.to_string() })?;
+    let text = handlebars
+        .render("frontmatter", context)
+        .map_err(|e| NnError::FailedToRenderFrontmatterTemplate { arg: e.to_string() })?;
+
+    Ok(text)
+}
+
+fn register_helpers(handlebars: &mut Handlebars, slug_options: &SlugOptions) {
+    handlebars.register_helper(
+        "slugify",
+        Box::new(SlugifyHelper {
+            options: slug_options.clone(),
+        }),
+    );
+    handlebars.register_helper("date", Box::new(date_helper));
+    handlebars.register_helper("upper", Box::new(upper_helper));
+    handlebars.register_helper("lower", Box::new(lower_helper));
+    handlebars.register_helper("default", Box::new(default_helper));
+    handlebars.register_helper("join", Box::new(join_helper));
+}
+
+fn param<'a>(h: &'a Helper, idx: usize) -> Result<&'a JsonValue, RenderError> {
+    h.param(idx).map(|p| p.value()).ok_or_else(|| {
+        RenderError::new(format!(
+            "Parameter {} missing for helper '{}'",
+            idx,
+            h.name()
+        ))
+    })
+}
+
+fn write_escaped(r: &Handlebars, out: &mut dyn Output, value: &str) -> HelperResult {
+    out.write(&r.get_escape_fn()(value))?;
+    Ok(())
+}
+
+/// `{{slugify title}}` with the configured slug options.
+struct SlugifyHelper {
+    options: SlugOptions,
+}
+
+impl HelperDef for SlugifyHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let value = param(h, 0)?.render();
+        write_escaped(r, out, &slugify(&value, &self.options))
+    }
+}
+
+/// `{{date date "%d.%m.%Y"}}`; the format defaults to `%Y-%m-%d`.
+fn date_helper(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let date = param(h, 0)?.render();
+    let format = h
+        .param(1)
+        .map(|p| p.value().render())
+        .unwrap_or_else(|| "%Y-%m-%d".to_string());
+    let formatted = format_date(&date, &format).ok_or_else(|| {
+        RenderError::new(format!(
+            "Could not format date '{}' with '{}'",
+            date, format
+        ))
+    })?;
+    write_escaped(r, out, &formatted)
+}
+
+fn format_date(date: &str, format: &str) -> Option<String> {
+    let date = parse_frontmatter_date(date)?;
+
+    // Invalid format specifiers make formatting fail instead of panicking like `to_string`.
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(format)).ok()?;
+
+    Some(formatted)
+}
+
+/// `{{upper title}}`
+fn upper_helper(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    write_escaped(r, out, &param(h, 0)?.render().to_uppercase())
+}
+
+/// `{{lower title}}`
+fn lower_helper(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    write_escaped(r, out, &param(h, 0)?.render().to_lowercase())
+}
+
+/// `{{default description "No description"}}` renders the fallback if the value is missing or
+/// empty.
+fn default_helper(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = param(h, 0)?;
+    let fallback = param(h, 1)?;
+    let is_empty = match value {
+        JsonValue::Null => true,
+        JsonValue::String(s) => s.is_empty(),
+        JsonValue::Array(a) => a.is_empty(),
+        _ => false,
+    };
+    let value = if is_empty { fallback } else { value };
+    write_escaped(r, out, &render_json(value, ", "))
+}
+
+/// `{{join tags ", "}}`; the separator defaults to `, `.
+fn join_helper(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = param(h, 0)?;
+    let separator = h
+        .param(1)
+        .map(|p| p.value().render())
+        .unwrap_or_else(|| ", ".to_string());
+    write_escaped(r, out, &render_json(value, &separator))
+}
+
+fn render_json(value: &JsonValue, separator: &str) -> String {
+    match value {
+        JsonValue::Array(values) => values
+            .iter()
+            .map(|v| v.render())
+            .collect::<Vec<_>>()
+            .join(separator),
+        _ => value.render(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn render_template_okay() {
+        let frontmatter = FrontMatter::new("Über Rust", "2001-01-01");
+        let context = TemplateContext::new(&frontmatter, "ueber-rust");
+        let template = NoteTemplate::new("{{frontmatter.title}}|{{title}}|{{date}}|{{slug}}");
+
+        let res = render_template(&template, &context);
+
+        assert_that(&res)
+            .is_ok()
+            .is_equal_to("Über Rust|Über Rust|2001-01-01|ueber-rust".to_owned());
+    }
+
+    #[test]
+    fn template_variables_take_precedence() {
+        let mut frontmatter = FrontMatter::new("Über Rust", "2001-01-01");
+        frontmatter.set_value("slug", "custom");
+        let context = TemplateContext::new(&frontmatter, "ueber-rust");
+        let template = NoteTemplate::new("{{slug}}|{{frontmatter.slug}}");
+
+        let res = render_template(&template, &context);
+
+        assert_that(&res)
+            .is_ok()
+            .is_equal_to("ueber-rust|custom".to_owned());
+    }
+
+    #[test]
+    fn render_template_with_list() {
+        let mut frontmatter = FrontMatter::new("Title", "2001-01-01");
+        frontmatter.add_to_list("tags", "rust");
+        frontmatter.add_to_list("tags", "cli");
+        let context = TemplateContext::new(&frontmatter, "title");
+        let template = NoteTemplate::new("{{#each frontmatter.tags}}- {{this}}\n{{/each}}");
+
+        let res = render_template(&template, &context);
+
+        assert_that(&res)
+            .is_ok()
+            .is_equal_to("- rust\n- cli\n".to_owned());
+    }
+
+    mod helpers {
+        use super::*;
+
+        fn render(template: &str) -> Result<String, NnError> {
+            let mut frontmatter = FrontMatter::new("Über Rust", "2001-02-03 04:05");
+            frontmatter.add_to_list("tags", "rust");
+            frontmatter.add_to_list("tags", "cli");
+            let context = TemplateContext::new(&frontmatter, "ueber-rust");
+            render_template(&NoteTemplate::new(template), &context)
+        }
+
+        #[test]
+        fn slugify() {
+            let res = render("{{slugify title}}");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to("ueber-rust".to_owned());
+        }
+
+        #[test]
+        fn slugify_with_slug_options() {
+            let frontmatter = FrontMatter::new("Über lange Titel", "2001-02-03");
+            let options = SlugOptions {
+                language: "en".to_owned(),
+                max_length: 10,
+            };
+            let context =
+                TemplateContext::new(&frontmatter, "uber-lange").with_slug_options(&options);
+
+            let res = render_template(&NoteTemplate::new("{{slugify title}}"), &context);
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to("uber-lange".to_owned());
+        }
+
+        #[test]
+        fn date() {
+            let res = render("{{date}}|{{date date}}|{{date date \"%d.%m.%Y %H:%M\"}}");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to("2001-02-03 04:05|2001-02-03|03.02.2001 04:05".to_owned());
+        }
+
+        #[test]
+        fn date_invalid() {
+            let res = render("{{date title}}");
+
+            assert_that(&res).is_err();
+        }
+
+        #[test]
+        fn upper_lower() {
+            let res = render("{{upper title}}|{{lower title}}");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to("ÜBER RUST|über rust".to_owned());
+        }
+
+        #[test]
+        fn default() {
+            let res = render("{{default description \"none\"}}|{{default title \"none\"}}");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to("none|Über Rust".to_owned());
+        }
+
+        #[test]
+        fn join() {
+            let res = render("{{join tags}}|{{join tags \" \"}}|{{join title}}");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to("rust, cli|rust cli|Über Rust".to_owned());
+        }
+    }
+
+    mod templates {
+        use super::*;
+
+        fn template_dir(name: &str, files: &[(&str, &str)]) -> TempDir {
+            let dir = TempDir::new(&format!("templates-{}", name));
+            for (file, content) in files {
+                fs::write(dir.join(file), content).expect("Failed to write template");
+            }
+            dir
+        }
+
+        #[test]
+        fn load_inline() {
+            let res = NoteTemplate::load(&TemplateSource::Inline("{{title}}".to_owned()), "note");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to(NoteTemplate::new("{{title}}"));
+        }
+
+        #[test]
+        fn load_inline_path_is_a_template() {
+            let dir = template_dir("inline-path", &[("post.hbs", "{{title}}\n")]);
+            let path = dir.join("post.hbs").to_str().unwrap().to_owned();
+
+            let res = NoteTemplate::load(&TemplateSource::Inline(path.clone()), "note");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to(NoteTemplate::new(&path));
+        }
+
+        #[test]
+        fn load_file() {
+            let dir = template_dir("file", &[("post.hbs", "{{title}}\n")]);
+
+            let res = NoteTemplate::load(&TemplateSource::File(dir.join("post.hbs")), "note");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to(NoteTemplate::new("{{title}}\n"));
+        }
+
+        #[test]
+        fn load_missing_file() {
+            let source = TemplateSource::File(PathBuf::from("/does/not/exist.hbs"));
+
+            let res = NoteTemplate::load(&source, "note");
+
+            assert_that(&res).is_err();
+        }
+
+        #[test]
+        fn load_dir_with_partials() {
+            let dir = template_dir(
+                "dir",
+                &[
+                    ("note.hbs", "note {{> footer}}"),
+                    ("til.hbs", "til {{> footer}}"),
+                    ("footer.hbs", "by {{author}}"),
+                    ("README.md", "not a template"),
+                ],
+            );
+            let source = TemplateSource::File(dir.to_path_buf());
+
+            let til = NoteTemplate::load(&source, "til");
+            let post = NoteTemplate::load(&source, "post");
+
+            assert_that(&til)
+                .is_ok()
+                .map(|t| &t.template)
+                .is_equal_to("til {{> footer}}".to_owned());
+            assert_that(&post)
+                .is_ok()
+                .map(|t| &t.template)
+                .is_equal_to("note {{> footer}}".to_owned());
+            let partials: Vec<_> = post.unwrap().partials.keys().cloned().collect();
+            assert_that(&partials).is_equal_to(vec!["footer".to_owned(), "til".to_owned()]);
+        }
+
+        #[test]
+        fn load_dir_without_template() {
+            let dir = template_dir("empty", &[("footer.hbs", "footer")]);
+
+            let res = NoteTemplate::load(&TemplateSource::File(dir.to_path_buf()), "note");
+
+            assert_that(&res).is_err();
+        }
+
+        #[test]
+        fn render_with_partial() {
+            let mut template = NoteTemplate::new("{{title}}\n{{> footer}}");
+            template
+                .partials
+                .insert("footer".to_owned(), "-- {{upper slug}}".to_owned());
+            let frontmatter = FrontMatter::new("Title", "2001-01-01");
+            let context = TemplateContext::new(&frontmatter, "title");
+
+            let res = render_template(&template, &context);
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to("Title\n-- TITLE".to_owned());
+        }
+    }
+
+    mod body {
+        use super::*;
+
+        #[test]
+        fn append_body_okay() {
+            let res = append_body("---\ntitle: x\n---\n\n\n", "\n\nHello\nWorld");
+
+            assert_that(&res.as_str()).is_equal_to("---\ntitle: x\n---\n\nHello\nWorld\n");
+        }
+
+        #[test]
+        fn append_empty_body() {
+            let res = append_body("---\n---\n\n", "\n");
+
+            assert_that(&res.as_str()).is_equal_to("---\n---\n\n");
+        }
+
+        #[test]
+        fn split_note_with_frontmatter() {
+            let mut expected = FrontMatter::default();
+            expected.set_value("title", "Draft");
+            expected.add_to_list("tags", "rust");
+
+            let res = split_note("---\ntitle: Draft\ntags: [rust]\n---\nBody\n");
+
+            assert_that(&res).is_ok().is_equal_to((expected, "Body\n"));
+        }
+
+        #[test]
+        fn split_note_without_frontmatter() {
+            let res = split_note("# Draft\n\nBody\n");
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to((FrontMatter::default(), "# Draft\n\nBody\n"));
+        }
+
+        #[test]
+        fn merge_frontmatter() {
+            let mut frontmatter = FrontMatter::new("Title", "2001-01-01");
+            frontmatter.set_value("author", "lukas");
+            let mut imported = FrontMatter::default();
+            imported.set_value("date", "2020-06-10");
+            imported.add_to_list("tags", "rust");
+
+            frontmatter.merge(imported);
+
+            assert_that(&frontmatter.get_value("title")).is_equal_to(Some("Title"));
+            assert_that(&frontmatter.get_value("date")).is_equal_to(Some("2020-06-10"));
+            assert_that(&frontmatter.get_value("author")).is_equal_to(Some("lukas"));
+            assert_that(&frontmatter.get_list("tags")).is_equal_to(vec!["rust"]);
+        }
+
+        #[test]
+        fn create_note_with_body() {
+            let dir = TempDir::new("new_note-body");
+            let path = dir.join("note.md");
+            let frontmatter = FrontMatter::new("Title", "2001-01-01");
+            let context = TemplateContext::new(&frontmatter, "title").with_body("Body");
+
+            let res = create_note(&path, &NoteTemplate::new("# {{title}}\n"), &context);
+
+            assert_that(&res)
+                .is_ok()
+                .is_equal_to("# Title\n\nBody\n".to_owned());
+        }
+    }
+}
@@ -0,0 +1,542 @@
+use super::*;
+use chrono::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Checks whether the note at `path` still has its original `content`.
+pub fn is_note_unchanged(path: &Path, content: &str) -> Result<bool, NnError> {
+    let current = fs::read_to_string(path)
+        .map_err(|e| NnError::FailedToWriteNoteFile { arg: e.to_string() })?;
+
+    Ok(current == content)
+}
+
+/// Deletes the note at `path` as well as its directories in case they became empty, but never
+/// `notes_directory` itself.
+pub fn delete_note(path: &Path, notes_directory: &Path) -> Result<(), NnError> {
+    fs::remove_file(path).map_err(|e| NnError::FailedToWriteNoteFile { arg: e.to_string() })?;
+
+    let mut dir = path.parent();
+    while let Some(d) = dir {
+        if d == notes_directory || !d.starts_with(notes_directory) || fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+
+    Ok(())
+}
+
+/// Returns where the existing note `path` belongs according to `pattern` in `directory`; page
+/// bundles stay page bundles.
+pub fn note_target_path(
+    path: &Path,
+    directory: &Path,
+    pattern: &str,
+    date: &DateTime<Local>,
+    slug: &str,
+    kind: &str,
+) -> Result<PathBuf, NnError> {
+    let target = directory.join(note_path(pattern, date, slug, kind)?);
+    if is_page_bundle(path) {
+        Ok(bundle_path(&target))
+    } else {
+        Ok(target)
+    }
+}
+
+/// Returns the URL path of the note `path` relative to `notes_directory`, e.g.,
+/// "/2020-06-10/hello/" for both "2020-06-10/hello.md" and "2020-06-10/hello/index.md".
+pub fn note_alias(path: &Path, notes_directory: &Path) -> Option<String> {
+    let relative = match path.parent() {
+        Some(bundle) if is_page_bundle(path) => {
+            bundle.strip_prefix(notes_directory).ok()?.to_path_buf()
+        }
+        _ => path.strip_prefix(notes_directory).ok()?.with_extension(""),
+    };
+    let components: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    if components.is_empty() {
+        return None;
+    }
+
+    Some(format!("/{}/", components.join("/")))
+}
+
+/// Sets the title of the note `path` to `title` and moves the note, or its page bundle, to
+/// `new_path`, cf. `note_target_path`. If `alias` is given, it is added to the frontmatter
+/// field `aliases`.
+pub fn retitle_note(
+    path: &Path,
+    title: &str,
+    new_path: &Path,
+    alias: Option<&str>,
+) -> Result<(), NnError> {
+    update_note(path, new_path, |frontmatter| {
+        frontmatter.set_value("title", title);
+        if let Some(alias) = alias {
+            if !frontmatter.get_list("aliases").contains(&alias) {
+                frontmatter.add_to_list("aliases", alias);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Removes the draft flag of the note `path`, sets its frontmatter field date to `date`, and
+/// moves the note, or its page bundle, to `new_path`.
+pub fn publish_note(path: &Path, date: &str, new_path: &Path) -> Result<(), NnError> {
+    update_note(path, new_path, |frontmatter| {
+        if !frontmatter.is_draft() {
+            return Err(NnError::InvalidFrontMatter {
+                arg: format!("'{}' is not a draft", path.display()),
+            });
+        }
+        frontmatter.fields.remove("draft");
+        frontmatter.set_value("date", date);
+        Ok(())
+    })
+}
+
+/// Updates the frontmatter of the note `path` with `update` and moves the note, or its page
+/// bundle, so that it ends up at `new_path`. Existing files are never overwritten. Only the
+/// changed fields of YAML and TOML frontmatters are rewritten, so comments and fields, which
+/// can't be represented by `FrontMatter`, are kept.
+pub fn update_note<F>(path: &Path, new_path: &Path, update: F) -> Result<(), NnError>
+where
+    F: FnOnce(&mut FrontMatter) -> Result<(), NnError>,
+{
+    let content = fs::read_to_string(path).map_err(|e| NnError::FailedToReadNote {
+        arg: format!("failed to read '{}' because {}", path.display(), e),
+    })?;
+    let format =
+        FrontMatterFormat::detect(&content).ok_or_else(|| NnError::InvalidFrontMatter {
+            arg: format!("'{}' has no frontmatter", path.display()),
+        })?;
+    let (frontmatter, body) = parse_frontmatter_as(&content, format)?;
+    let mut updated = frontmatter.clone();
+    update(&mut updated)?;
+    let block = match format {
+        FrontMatterFormat::Json => frontmatter_block(&updated, format)?,
+        _ => {
+            let block = &content[..content.len() - body.len()];
+            update_frontmatter_fields(block, format, &frontmatter, &updated)?
+        }
+    };
+    let content = format!("{}{}", block, body);
+
+    let (from, to) = match (path.parent(), new_path.parent()) {
+        (Some(from), Some(to)) if is_page_bundle(path) => (from, to),
+        _ => (path, new_path),
+    };
+    if from != to {
+        if to.exists() {
+            return Err(NnError::NoteAlreadyExists {
+                arg: to.display().to_string(),
+            });
+        }
+        let move_err = |e: std::io::Error| NnError::FailedToWriteNoteFile {
+            arg: format!(
+                "failed to move '{}' to '{}' because {}",
+                from.display(),
+                to.display(),
+                e
+            ),
+        };
+        if let Some(dir) = to.parent() {
+            fs::create_dir_all(dir).map_err(move_err)?;
+        }
+        fs::rename(from, to).map_err(move_err)?;
+    }
+
+    replace_note_content(&content, new_path)
+}
+
+/// Replaces the fields of the YAML or TOML frontmatter `block` which differ between `old` and
+/// `new` line by line; all other lines are kept as they are.
+fn update_frontmatter_fields(
+    block: &str,
+    format: FrontMatterFormat,
+    old: &FrontMatter,
+    new: &FrontMatter,
+) -> Result<String, NnError> {
+    let mut lines: Vec<String> = block.split_inclusive('\n').map(String::from).collect();
+    if lines.len() < 2 {
+        return Err(NnError::InvalidFrontMatter {
+            arg: "missing frontmatter delimiters".to_string(),
+        });
+    }
+    let closing = lines.pop().unwrap_or_default();
+    let opening = lines.remove(0);
+
+    let keys: std::collections::BTreeSet<_> = old.fields.keys().chain(new.fields.keys()).collect();
+    for key in keys {
+        let value = new.fields.get(key);
+        if old.fields.get(key) == value {
+            continue;
+        }
+
+        let (entries, insert_at) = frontmatter_entries(&lines, format)?;
+        let span = entries
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, span)| span);
+        let field = match value {
+            Some(value) => {
+                let existing = span.as_ref().map(|span| lines[span.clone()].concat());
+                frontmatter_field(key, value, format, existing.as_deref())?
+            }
+            None => String::new(),
+        };
+        match span {
+            Some(span) => {
+                lines.splice(span, Some(field));
+            }
+            None if old.fields.contains_key(key) => {
+                return Err(NnError::InvalidFrontMatter {
+                    arg: format!("field '{}' can't be updated", key),
+                })
+            }
+            // Tables must come last in TOML.
+            None if field.starts_with('[') => lines.push(field),
+            None => lines.insert(insert_at, field),
+        }
+        lines = lines
+            .concat()
+            .split_inclusive('\n')
+            .map(String::from)
+            .collect();
+    }
+
+    Ok(format!("{}{}{}", opening, lines.concat(), closing))
+}
+
+/// Top-level keys of a frontmatter with the range of their lines
+type FrontMatterEntries = Vec<(String, std::ops::Range<usize>)>;
+
+/// Returns the top-level keys of the frontmatter `lines` with their line ranges, and the line
+/// new fields are inserted at.
+fn frontmatter_entries(
+    lines: &[String],
+    format: FrontMatterFormat,
+) -> Result<(FrontMatterEntries, usize), NnError> {
+    let mut entries = Vec::new();
+    let mut insert_at = 0;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = &lines[i];
+        let trimmed = line.trim();
+        let is_list_item = trimmed == "-" || trimmed.starts_with("- ");
+        if trimmed.is_empty()
+            || trimmed.starts_with('#')
+            || line.starts_with(char::is_whitespace)
+            || is_list_item
+        {
+            i += 1;
+            continue;
+        }
+
+        let (key, end) = match format {
+            FrontMatterFormat::Toml => {
+                // Top-level keys come before all tables.
+                if trimmed.starts_with('[') {
+                    break;
+                }
+                // A value ends with the first line that completes it, e.g., a multi-line array.
+                let (table, end) = (i + 1..=lines.len())
+                    .find_map(|end| {
+                        toml::from_str::<toml::value::Table>(&lines[i..end].concat())
+                            .ok()
+                            .map(|table| (table, end))
+                    })
+                    .ok_or_else(|| NnError::InvalidFrontMatter {
+                        arg: format!("can't parse '{}'", trimmed),
+                    })?;
+                let key = table.into_iter().next().map(|(key, _)| key);
+                (key, end)
+            }
+            _ => {
+                let key = line
+                    .split(':')
+                    .next()
+                    .and_then(|k| parse_yaml_value(k).ok());
+                // Lists and nested structures continue with indented or list item lines.
+                let mut end = i + 1;
+                for (j, line) in lines.iter().enumerate().skip(i + 1) {
+                    let trimmed = line.trim();
+                    if trimmed.is_empty() {
+                        continue;
+                    }
+                    if !line.starts_with(char::is_whitespace)
+                        && trimmed != "-"
+                        && !trimmed.starts_with("- ")
+                    {
+                        break;
+                    }
+                    end = j + 1;
+                }
+                (key, end)
+            }
+        };
+        if let Some(key) = key {
+            entries.push((key, i..end));
+        }
+        i = end;
+        insert_at = end;
+    }
+    if format != FrontMatterFormat::Toml {
+        insert_at = lines.len();
+    }
+
+    Ok((entries, insert_at))
+}
+
+/// Renders the single frontmatter field `key`; a TOML date stays a date if `existing` is one.
+fn frontmatter_field(
+    key: &str,
+    value: &FrontMatterType,
+    format: FrontMatterFormat,
+    existing: Option<&str>,
+) -> Result<String, NnError> {
+    let render_err =
+        |e: toml::ser::Error| NnError::FailedToRenderFrontmatterTemplate { arg: e.to_string() };
+
+    if let (FrontMatterFormat::Toml, FrontMatterType::Value(value), Some(existing)) =
+        (format, value, existing)
+    {
+        let was_date = toml::from_str::<toml::value::Table>(existing)
+            .ok()
+            .and_then(|table| table.get(key).map(|v| v.is_datetime()))
+            .unwrap_or(false);
+        if let (true, Ok(date)) = (was_date, value.parse::<toml::value::Datetime>()) {
+            let mut table = toml::value::Table::new();
+            table.insert(key.to_string(), toml::Value::Datetime(date));
+            return toml::to_string(&table).map_err(render_err);
+        }
+    }
+
+    let mut frontmatter = FrontMatter::default();
+    frontmatter.fields.insert(key.to_string(), value.clone());
+    let block = frontmatter_block(&frontmatter, format)?;
+    let delimiter = match format {
+        FrontMatterFormat::Toml => "+++\n",
+        _ => "---\n",
+    };
+
+    Ok(block
+        .strip_prefix(delimiter)
+        .and_then(|b| b.strip_suffix(delimiter))
+        .unwrap_or(&block)
+        .to_string())
+}
+
+#[cfg(test)]
+mod test {
+    pub use super::*;
+    pub use crate::test_util::TempDir;
+    pub use spectral::prelude::*;
+
+    #[test]
+    fn delete_note_removes_empty_directories() {
+        let notes = TempDir::new("new_note-delete");
+        let other = notes.join("2020/other.md");
+        let path = notes.join("2020/06/hello/index.md");
+        write_content_to_file("Other", &other).unwrap();
+        write_content_to_file("Hello", &path).unwrap();
+
+        let unchanged = is_note_unchanged(&path, "Hello");
+        let res = delete_note(&path, &notes);
+
+        assert_that(&unchanged).is_ok().is_true();
+        assert_that(&res).is_ok();
+        assert_that(&notes.join("2020/06").exists()).is_false();
+        assert_that(&other.exists()).is_true();
+    }
+
+    mod retitle {
+        use super::*;
+
+        #[test]
+        fn target_paths_of_notes_and_bundles() {
+            let date = Local.ymd(2026, 10, 20).and_hms(09, 00, 00);
+            let target = |path: &str, pattern: &str| {
+                note_target_path(
+                    Path::new(path),
+                    Path::new("notes"),
+                    pattern,
+                    &date,
+                    "new",
+                    "note",
+                )
+            };
+
+            assert_that(&target("notes/2026-10-20-old.md", "{date}-{slug}.md"))
+                .is_ok()
+                .is_equal_to(PathBuf::from("notes/2026-10-20-new.md"));
+            assert_that(&target("notes/2026/old/index.md", "{year}/{slug}.md"))
+                .is_ok()
+                .is_equal_to(PathBuf::from("notes/2026/new/index.md"));
+        }
+
+        #[test]
+        fn note_aliases() {
+            let notes = Path::new("/notes");
+
+            assert_that(&note_alias(Path::new("/notes/2020/old.md"), notes))
+                .is_equal_to(Some("/2020/old/".to_owned()));
+            assert_that(&note_alias(Path::new("/notes/2020/old/index.md"), notes))
+                .is_equal_to(Some("/2020/old/".to_owned()));
+            assert_that(&note_alias(Path::new("/elsewhere/old.md"), notes)).is_none();
+        }
+
+        #[test]
+        fn retitle_note_file() {
+            let notes = TempDir::new("new_note-retitle-file");
+            let path = notes.join("2020/old-title.md");
+            write_content_to_file(
+                "---\ntitle: \"Old Title\"\ndate: \"2020-06-10\"\n---\n\nBody\n",
+                &path,
+            )
+            .unwrap();
+            let alias = note_alias(&path, &notes).unwrap();
+            let new_path = notes.join("2020/new-title.md");
+
+            let res = retitle_note(&path, "New Title", &new_path, Some(&alias));
+
+            assert_that(&res).is_ok();
+            assert_that(&path.exists()).is_false();
+            assert_that(&fs::read_to_string(notes.join("2020/new-title.md")).unwrap()).is_equal_to(
+                "---\ntitle: \"New Title\"\ndate: \"2020-06-10\"\naliases:\n- \"/2020/old-title/\"\n---\n\nBody\n"
+                    .to_owned(),
+            );
+        }
+
+        #[test]
+        fn retitle_with_date_prefix() {
+            let notes = TempDir::new("new_note-retitle-date");
+            let path = notes.join("2026-10-20-old-title.md");
+            write_content_to_file("---\ntitle: Old Title\ndate: 2026-10-20\n---\n", &path).unwrap();
+            let note = read_note(&path).unwrap().unwrap();
+            let new_path = note_target_path(
+                &path,
+                &notes,
+                "{date}-{slug}.md",
+                &note.datetime().unwrap(),
+                "new-title",
+                "note",
+            )
+            .unwrap();
+
+            let res = retitle_note(&path, "New Title", &new_path, None);
+
+            assert_that(&res).is_ok();
+            assert_that(&new_path).is_equal_to(notes.join("2026-10-20-new-title.md"));
+            assert_that(&new_path.exists()).is_true();
+        }
+
+        #[test]
+        fn retitle_keeps_other_fields() {
+            let notes = TempDir::new("new_note-retitle-fields");
+            let yaml = notes.join("old.md");
+            let toml = notes.join("toml/old.md");
+            write_content_to_file(
+                "---\n# A comment\ntitle: Old\nweight: 10\ndraft: false\nauthor: {\"name\": \"lukas\"}\ntags:\n- rust\n---\nBody\n",
+                &yaml,
+            )
+            .unwrap();
+            write_content_to_file(
+                "+++\ntitle = \"Old\" # the title\ndate = 2020-06-10\nweight = 10\n\n[extra]\nlang = \"en\"\n+++\nBody\n",
+                &toml,
+            )
+            .unwrap();
+
+            let yaml_res = retitle_note(&yaml, "New", &notes.join("new.md"), Some("/old/"));
+            let toml_res = retitle_note(&toml, "New", &notes.join("toml/new.md"), Some("/old/"));
+
+            assert_that(&yaml_res).is_ok();
+            assert_that(&toml_res).is_ok();
+            assert_that(&fs::read_to_string(notes.join("new.md")).unwrap()).is_equal_to(
+                "---\n# A comment\ntitle: \"New\"\nweight: 10\ndraft: false\nauthor: {\"name\": \"lukas\"}\ntags:\n- rust\naliases:\n- \"/old/\"\n---\nBody\n"
+                    .to_owned(),
+            );
+            assert_that(&fs::read_to_string(notes.join("toml/new.md")).unwrap()).is_equal_to(
+                "+++\ntitle = \"New\"\ndate = 2020-06-10\nweight = 10\naliases = [\"/old/\"]\n\n[extra]\nlang = \"en\"\n+++\nBody\n"
+                    .to_owned(),
+            );
+        }
+
+        #[test]
+        fn retitle_page_bundle() {
+            let notes = TempDir::new("new_note-retitle-bundle");
+            let path = notes.join("2020/old/index.md");
+            write_content_to_file("+++\ntitle = \"Old\"\n+++\nBody\n", &path).unwrap();
+            write_content_to_file("png", &notes.join("2020/old/img.png")).unwrap();
+
+            let res = retitle_note(&path, "New", &notes.join("2020/new/index.md"), None);
+
+            assert_that(&res).is_ok();
+            assert_that(&notes.join("2020/new/img.png").exists()).is_true();
+            assert_that(&fs::read_to_string(notes.join("2020/new/index.md")).unwrap())
+                .is_equal_to("+++\ntitle = \"New\"\n+++\nBody\n".to_owned());
+        }
+
+        #[test]
+        fn publish_draft_bundle() {
+            let notes = TempDir::new("new_note-retitle-publish");
+            let draft = notes.join("drafts/hello/index.md");
+            write_content_to_file(
+                "---\ntitle: Hello\ndate: 2020-01-01\ndraft: true\n---\nBody\n",
+                &draft,
+            )
+            .unwrap();
+            write_content_to_file("png", &notes.join("drafts/hello/img.png")).unwrap();
+            let path = notes.join("2020-06-10/hello/index.md");
+
+            let res = publish_note(&draft, "2020-06-10", &path);
+            let again = publish_note(&path, "2020-06-11", &notes.join("hello.md"));
+
+            assert_that(&res).is_ok();
+            assert_that(&again).is_err();
+            assert_that(&notes.join("drafts/hello").exists()).is_false();
+            assert_that(&notes.join("2020-06-10/hello/img.png").exists()).is_true();
+            assert_that(&fs::read_to_string(&path).unwrap())
+                .is_equal_to("---\ntitle: Hello\ndate: \"2020-06-10\"\n---\nBody\n".to_owned());
+        }
+
+        #[test]
+        fn publish_toml_draft() {
+            let notes = TempDir::new("new_note-publish-toml");
+            let draft = notes.join("drafts/hello.md");
+            write_content_to_file(
+                "+++\ntitle = \"Hello\"\ndate = 2020-01-01\ndraft = true\nweight = 10\n\n# Theme settings\n[extra]\nlang = \"en\"\n+++\nBody\n",
+                &draft,
+            )
+            .unwrap();
+            let path = notes.join("2020-06-10/hello.md");
+
+            let res = publish_note(&draft, "2020-06-10", &path);
+
+            assert_that(&res).is_ok();
+            assert_that(&fs::read_to_string(&path).unwrap()).is_equal_to(
+                "+++\ntitle = \"Hello\"\ndate = 2020-06-10\nweight = 10\n\n# Theme settings\n[extra]\nlang = \"en\"\n+++\nBody\n"
+                    .to_owned(),
+            );
+        }
+
+        #[test]
+        fn retitle_does_not_overwrite() {
+            let notes = TempDir::new("new_note-retitle-exists");
+            let path = notes.join("old.md");
+            write_content_to_file("---\ntitle: Old\n---\n", &path).unwrap();
+            write_content_to_file("Other", &notes.join("new.md")).unwrap();
+
+            let res = retitle_note(&path, "New", &notes.join("new.md"), None);
+
+            assert_that(&matches!(res, Err(NnError::NoteAlreadyExists { .. }))).is_true();
+            assert_that(&fs::read_to_string(&path).unwrap())
+                .is_equal_to("---\ntitle: Old\n---\n".to_owned());
+        }
+    }
+}